@@ -0,0 +1,85 @@
+//! Open the web server behind a port in the browser.
+
+use portwitch::{Family, Protocol, Socket, services};
+use std::io;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Ports that development servers like to use, in addition to the `http` services.
+const WEB_PORTS: [u16; 10] = [3000, 3001, 4000, 4200, 5000, 5173, 8000, 8080, 8443, 8888];
+
+/// The command that opens a URL with the default browser.
+#[cfg(target_os = "macos")]
+const OPENER: (&str, &[&str]) = ("open", &[]);
+#[cfg(windows)]
+const OPENER: (&str, &[&str]) = ("cmd", &["/C", "start", ""]);
+#[cfg(all(unix, not(target_os = "macos")))]
+const OPENER: (&str, &[&str]) = ("xdg-open", &[]);
+
+/// A TCP listener on a port that usually serves HTTP.
+pub fn is_web(socket: &Socket) -> bool {
+    let service = services::name(socket.protocol, socket.port);
+    socket.protocol == Protocol::Tcp
+        && socket.remote.is_none()
+        && (WEB_PORTS.contains(&socket.port) || service.is_some_and(|s| s.starts_with("http")))
+}
+
+/// A URL like `http://localhost:3000`. Sockets bound to a single address that is not
+/// a loopback one can only be reached there.
+pub fn url(socket: &Socket) -> String {
+    let scheme = match socket.port {
+        443 | 8443 => "https",
+        _ => "http",
+    };
+    let host = match socket.family {
+        _ if socket.address == "*" || socket.is_loopback() => "localhost".to_string(),
+        Family::V6 => format!("[{}]", socket.address),
+        Family::V4 => socket.address.clone(),
+    };
+    format!("{scheme}://{host}:{}", socket.port)
+}
+
+/// Open the URL in the background, the browser may take a while.
+pub fn open(url: &str) -> io::Result<()> {
+    let (program, args) = OPENER;
+    let mut child = Command::new(program)
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("{program} not found")),
+            _ => e,
+        })?;
+    // Reap it, so it does not stay around as a zombie
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        let url = |local: &str| url(&Socket::parse_local(Protocol::Tcp, local).unwrap());
+        assert_eq!(url("*:3000"), "http://localhost:3000");
+        assert_eq!(url("127.0.0.1:443"), "https://localhost:443");
+        assert_eq!(url("[::1]:8080"), "http://localhost:8080");
+        assert_eq!(url("192.168.1.5:8000"), "http://192.168.1.5:8000");
+        assert_eq!(url("[fe80::1]:8000"), "http://[fe80::1]:8000");
+    }
+
+    #[test]
+    fn web_ports() {
+        let listener = |local: &str| Socket::parse_local(Protocol::Tcp, local).unwrap();
+        assert!(is_web(&listener("*:5173")));
+        assert!(is_web(&listener("*:80")));
+        assert!(!is_web(&listener("*:5432")));
+        let mut udp = listener("*:3000");
+        udp.protocol = Protocol::Udp;
+        assert!(!is_web(&udp));
+    }
+}
@@ -0,0 +1,297 @@
+//! Command line arguments.
+
+use crate::INTERVAL_LIMITS;
+use crate::theme::ThemeName;
+use itertools::Itertools;
+use portwitch::Protocol;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct Args {
+    /// The initial filter from `--filter`, or else made from all arguments that are not flags.
+    pub filter: String,
+    /// Print the process list in this format instead of starting the TUI.
+    pub export: Option<Export>,
+    /// Print a line whenever a port opens or closes instead of starting the TUI.
+    pub events: bool,
+    /// Show established connections, not only listeners.
+    pub connections: bool,
+    /// Count the connections of each listener, in addition to the config.
+    pub count_connections: bool,
+    /// Look up containers behind Docker proxies, in addition to the config.
+    pub docker: bool,
+    /// Only show processes of the current user.
+    pub mine: bool,
+    /// Only show this process and its descendants.
+    pub pid: Option<usize>,
+    /// Start with compact rows, without titles and header.
+    pub compact: bool,
+    /// Disable killing, in addition to the config.
+    pub read_only: bool,
+    /// Only show sockets of this protocol, both if `None`.
+    pub protocol: Option<Protocol>,
+    /// Ports to highlight, replacing the ones from the config.
+    pub watch_ports: Option<Vec<u16>>,
+    /// Also highlight the ports read from stdin, one per line.
+    pub watch_stdin: bool,
+    /// Replaces the theme from the config.
+    pub theme: Option<ThemeName>,
+    /// Use the plain theme, whatever the others say.
+    pub no_color: bool,
+    /// How often to scan, replacing the config and the interval set with `+` and `-`.
+    pub interval: Option<Duration>,
+    /// Only show processes reachable on this address.
+    pub address: Option<String>,
+    /// Only show processes reachable on the addresses of this network interface.
+    pub interface: Option<String>,
+    /// Only show processes with a port in this range, replacing the config.
+    pub min_port: Option<u16>,
+    pub max_port: Option<u16>,
+    /// Kill whatever listens on this port instead of starting the TUI.
+    pub reap: Option<u16>,
+    /// Reap a single time and exit.
+    pub once: bool,
+    /// Print the usage and exit.
+    pub help: bool,
+    /// Print the version and exit.
+    pub version: bool,
+}
+
+pub const USAGE: &str = "\
+Find the process blocking a port and kill it.
+
+Usage: portwitch [OPTIONS] [FILTER]...
+
+Arguments:
+  [FILTER]...  Initial filter, like `8080`, `node` or `:80`
+
+Options:
+  -f, --filter <FILTER>  Initial filter, used as is instead of [FILTER]
+      --connections      Show established connections, not only listeners
+      --conns            Count the established connections of each listener
+      --docker           Show the Docker container behind a port in the details
+      --compact          Show only rows of `pid command ports`, without titles
+      --mine             Only show processes of the current user
+      --pid <PID>        Only show this process and its children
+      --read-only        Never kill anything, only look
+      --tcp              Only show TCP sockets
+      --udp              Only show UDP sockets
+      --watch <PORTS>    Highlight processes on these ports, like `3000,8080`
+      --watch-stdin      Highlight the ports piped to stdin, one per line
+      --address <ADDR>   Only show processes reachable on this address, `*` for all
+      --iface <NAME>     Only show processes reachable on this network interface
+      --min-port <PORT>  Only show processes with a port from this one up
+      --max-port <PORT>  Only show processes with a port up to this one
+      --theme <THEME>    Color theme: dark, light or plain
+      --no-color         Don't use colors, like setting NO_COLOR
+      --interval <MS>    Scan every MS milliseconds, from 10 to 60000
+      --reap <PORT>      Kill whatever listens on the port, until interrupted
+      --once             With --reap, kill a single time and exit
+      --json             Print the processes as JSON and exit
+      --csv              Print the processes as CSV and exit
+      --events           Print a JSON line whenever a port opens or closes
+  -h, --help             Print help
+  -V, --version          Print version
+";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Export {
+    Json,
+    Csv,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut filter = Vec::new();
+        let mut args = args.into_iter();
+        let (mut tcp, mut udp) = (false, false);
+        let mut explicit_filter = None;
+
+        while let Some(arg) = args.next() {
+            // Flags with values can be given as `--flag value` or `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{flag} needs a value"))
+            };
+
+            match flag.as_str() {
+                "-f" | "--filter" => explicit_filter = Some(value()?),
+                "--json" => parsed.export = Some(Export::Json),
+                "--csv" => parsed.export = Some(Export::Csv),
+                "--events" => parsed.events = true,
+                "--connections" => parsed.connections = true,
+                "--conns" => parsed.count_connections = true,
+                "--docker" => parsed.docker = true,
+                "--compact" => parsed.compact = true,
+                "--read-only" => parsed.read_only = true,
+                "--mine" => parsed.mine = true,
+                "--pid" => {
+                    let pid = value()?;
+                    parsed.pid = Some(pid.parse().map_err(|_| format!("invalid PID `{pid}`"))?);
+                }
+                "--tcp" => tcp = true,
+                "--udp" => udp = true,
+                "--watch" => parsed.watch_ports = Some(parse_ports(&value()?)?),
+                "--watch-stdin" => parsed.watch_stdin = true,
+                "--theme" => parsed.theme = Some(value()?.parse()?),
+                "--no-color" => parsed.no_color = true,
+                "--interval" => parsed.interval = Some(parse_interval(&value()?)?),
+                "--address" => parsed.address = Some(value()?),
+                "--iface" => parsed.interface = Some(value()?),
+                "--min-port" => parsed.min_port = Some(parse_port(&value()?)?),
+                "--max-port" => parsed.max_port = Some(parse_port(&value()?)?),
+                "--reap" => parsed.reap = Some(parse_port(&value()?)?),
+                "--once" => parsed.once = true,
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                _ => filter.push(arg),
+            }
+        }
+
+        if parsed.once && parsed.reap.is_none() {
+            return Err("--once needs --reap".to_string());
+        }
+        if parsed.address.is_some() && parsed.interface.is_some() {
+            return Err("use either --address or --iface".to_string());
+        }
+        if parsed.read_only && parsed.reap.is_some() {
+            return Err("--reap kills, which --read-only does not allow".to_string());
+        }
+        if let (Some(min), Some(max)) = (parsed.min_port, parsed.max_port)
+            && min > max
+        {
+            return Err(format!("--min-port {min} is larger than --max-port {max}"));
+        }
+
+        // `--tcp --udp` is the same as neither
+        parsed.protocol = match (tcp, udp) {
+            (true, false) => Some(Protocol::Tcp),
+            (false, true) => Some(Protocol::Udp),
+            _ => None,
+        };
+        parsed.filter = explicit_filter.unwrap_or_else(|| filter.iter().join(" "));
+        Ok(parsed)
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    port.parse().map_err(|_| format!("invalid port `{port}`"))
+}
+
+fn parse_interval(ms: &str) -> Result<Duration, String> {
+    let ms: u64 = ms.parse().map_err(|_| format!("invalid interval `{ms}`"))?;
+    if !INTERVAL_LIMITS.contains(&ms) {
+        return Err(format!(
+            "--interval must be from {} to {} milliseconds, not {ms}",
+            INTERVAL_LIMITS.start(),
+            INTERVAL_LIMITS.end()
+        ));
+    }
+    Ok(Duration::from_millis(ms))
+}
+
+/// Parse a comma separated list of ports like `3000,8080`.
+fn parse_ports(list: &str) -> Result<Vec<u16>, String> {
+    list.split(',')
+        .map(|port| parse_port(port.trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn filter() {
+        assert_eq!(parse(&[]).unwrap().filter, "");
+        assert_eq!(parse(&["node", ":80"]).unwrap().filter, "node :80");
+        // Unknown flags are searched for, like a dash in a command
+        assert_eq!(
+            parse(&["--inspect", "-x=1"]).unwrap().filter,
+            "--inspect -x=1"
+        );
+        assert_eq!(parse(&["--unknown=1"]).unwrap().filter, "--unknown=1");
+        // --filter is used as is
+        assert_eq!(parse(&["node", "-f", "a b"]).unwrap().filter, "a b");
+        assert_eq!(parse(&["--filter=--json"]).unwrap().filter, "--json");
+        assert_eq!(parse(&["--filter"]).unwrap_err(), "--filter needs a value");
+    }
+
+    #[test]
+    fn reap() {
+        let args = parse(&["--reap", "3000", "--once"]).unwrap();
+        assert_eq!((args.reap, args.once), (Some(3000), true));
+        assert_eq!(parse(&["--reap=8080"]).unwrap().reap, Some(8080));
+        assert_eq!(
+            parse(&["--reap", "70000"]).unwrap_err(),
+            "invalid port `70000`"
+        );
+        assert_eq!(parse(&["--reap"]).unwrap_err(), "--reap needs a value");
+        assert_eq!(parse(&["--once"]).unwrap_err(), "--once needs --reap");
+        assert_eq!(
+            parse(&["--reap", "3000", "--read-only"]).unwrap_err(),
+            "--reap kills, which --read-only does not allow"
+        );
+    }
+
+    #[test]
+    fn interval() {
+        assert_eq!(parse(&[]).unwrap().interval, None);
+        assert_eq!(
+            parse(&["--interval", "250"]).unwrap().interval,
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(
+            parse(&["--interval=60000"]).unwrap().interval,
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            parse(&["--interval", "1s"]).unwrap_err(),
+            "invalid interval `1s`"
+        );
+        assert_eq!(
+            parse(&["--interval", "5"]).unwrap_err(),
+            "--interval must be from 10 to 60000 milliseconds, not 5"
+        );
+    }
+
+    #[test]
+    fn protocol() {
+        assert_eq!(parse(&[]).unwrap().protocol, None);
+        assert_eq!(parse(&["--tcp"]).unwrap().protocol, Some(Protocol::Tcp));
+        assert_eq!(parse(&["--udp"]).unwrap().protocol, Some(Protocol::Udp));
+        assert_eq!(parse(&["--udp", "--tcp"]).unwrap().protocol, None);
+    }
+
+    #[test]
+    fn flags() {
+        let args = parse(&["--read-only", "--json", "--watch", "3000, 8080", "--pid=42"]).unwrap();
+        assert!(args.read_only);
+        assert_eq!(args.export, Some(Export::Json));
+        assert_eq!(args.watch_ports, Some(vec![3000, 8080]));
+        assert_eq!(args.pid, Some(42));
+        assert!(!parse(&[]).unwrap().read_only);
+
+        assert_eq!(
+            parse(&["--min-port", "9000", "--max-port", "80"]).unwrap_err(),
+            "--min-port 9000 is larger than --max-port 80"
+        );
+        assert_eq!(
+            parse(&["--address", "*", "--iface", "lo"]).unwrap_err(),
+            "use either --address or --iface"
+        );
+    }
+}
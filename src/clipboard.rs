@@ -0,0 +1,77 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Tools that copy their stdin to the clipboard, in order of preference.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy text to the system clipboard with the first tool that works.
+pub fn copy(text: &str) -> io::Result<()> {
+    copy_with(TOOLS, text)
+}
+
+/// Try the tools in order. One that is installed can still fail, like `wl-copy`
+/// outside of Wayland or `xclip` without `$DISPLAY`, then the next one gets a turn.
+fn copy_with(tools: &[(&str, &[&str])], text: &str) -> io::Result<()> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no clipboard tool found");
+    for (program, args) in tools {
+        match run(program, args, text) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+fn run(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Take stdin so it's closed before waiting, otherwise the tool waits for more input.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()),
+        None => Ok(()),
+    };
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{program} failed with {status}")));
+    }
+    written
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_through_failing_tools() {
+        let tools: &[(&str, &[&str])] = &[
+            ("portwitch-no-such-tool", &[]),
+            ("false", &[]),
+            ("cat", &[]),
+        ];
+        assert!(copy_with(tools, "3000").is_ok());
+    }
+
+    #[test]
+    fn reports_the_last_failure() {
+        let tools: &[(&str, &[&str])] = &[("false", &[]), ("portwitch-no-such-tool", &[])];
+        let error = copy_with(tools, "3000").unwrap_err();
+        assert_eq!(error.to_string(), "false failed with exit status: 1");
+
+        let error = copy_with(&[("portwitch-no-such-tool", &[])], "3000").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "no clipboard tool found");
+    }
+}
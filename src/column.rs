@@ -0,0 +1,68 @@
+//! The columns of the process list.
+
+use ratatui::layout::Constraint;
+use std::str::FromStr;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// A column that can be configured with its name, e.g. `columns = ["command", "port"]`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Column {
+    Pid,
+    Command,
+    Address,
+    #[strum(to_string = "port")]
+    Ports,
+    /// The established connections to the listening ports, only shown if they are counted.
+    Conns,
+    /// Only shown if the uptime is looked up.
+    Uptime,
+    /// Only shown with established connections.
+    Remote,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 7] = [
+        Column::Pid,
+        Column::Command,
+        Column::Address,
+        Column::Ports,
+        Column::Conns,
+        Column::Uptime,
+        Column::Remote,
+    ];
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::Command => "Command",
+            Column::Address => "Address",
+            Column::Ports => "Ports",
+            Column::Conns => "Conns",
+            Column::Uptime => "Uptime",
+            Column::Remote => "Remote",
+        }
+    }
+
+    pub fn width(self) -> Constraint {
+        match self {
+            Column::Pid => Constraint::Length(8),
+            Column::Uptime => Constraint::Length(7),
+            Column::Conns => Constraint::Length(5),
+            // Like the others, but long commands can't squeeze the ports out of sight
+            Column::Ports => Constraint::Min(14),
+            Column::Command | Column::Address | Column::Remote => Constraint::Fill(1),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        Column::iter()
+            .find(|column| column.to_string() == name)
+            .ok_or_else(|| format!("unknown column `{s}`"))
+    }
+}
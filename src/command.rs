@@ -0,0 +1,82 @@
+//! Commands that can be typed after `:`, like `kill 8080`.
+
+use portwitch::kill::Signal;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Kill whatever listens on these ports, or the processes with these PIDs.
+    Kill {
+        numbers: Vec<usize>,
+        signal: Option<Signal>,
+    },
+}
+
+impl Command {
+    /// Parse commands like `kill 8080 3000` or `kill -KILL 1234`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut words = text.split_whitespace();
+        match words.next() {
+            Some("kill") => {
+                let mut words = words.peekable();
+                let signal = match words.next_if(|word| word.starts_with('-')) {
+                    Some(name) => Some(name[1..].parse()?),
+                    None => None,
+                };
+                let numbers = words
+                    .map(|word| {
+                        let number = word.strip_prefix(':').unwrap_or(word);
+                        number
+                            .parse()
+                            .map_err(|_| format!("invalid port or PID `{word}`"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if numbers.is_empty() {
+                    return Err("kill needs a port or PID".to_string());
+                }
+                Ok(Command::Kill { numbers, signal })
+            }
+            Some(command) => Err(format!("unknown command `{command}`")),
+            None => Err("no command".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kill() {
+        assert_eq!(
+            Command::parse("kill 8080 :3000"),
+            Ok(Command::Kill {
+                numbers: vec![8080, 3000],
+                signal: None
+            })
+        );
+        assert_eq!(
+            Command::parse(" kill -SIGKILL 1234 "),
+            Ok(Command::Kill {
+                numbers: vec![1234],
+                signal: Some(Signal::Kill)
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Command::parse("stop 80"),
+            Err("unknown command `stop`".to_string())
+        );
+        assert_eq!(
+            Command::parse("kill"),
+            Err("kill needs a port or PID".to_string())
+        );
+        assert_eq!(
+            Command::parse("kill node"),
+            Err("invalid port or PID `node`".to_string())
+        );
+        assert!(Command::parse("kill -FOO 80").is_err());
+    }
+}
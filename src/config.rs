@@ -0,0 +1,574 @@
+//! The config file at `~/.config/portwitch/config.toml`.
+//!
+//! Only the small subset of TOML that the config needs is supported:
+//! `key = value` pairs with basic and literal strings, integers, booleans and arrays of those,
+//! `[section]` headers and `#` comments.
+
+use crate::INTERVAL_LIMITS;
+use crate::column::Column;
+use crate::keymap::{Action, Keymap};
+use crate::theme::{ThemeName, parse_style};
+use portwitch::kill::Signal;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs, io};
+
+#[derive(Debug)]
+pub struct Config {
+    pub default_filter: String,
+    pub kill_signal: Signal,
+    /// Only set if the file has it, then it wins over the one changed in the UI.
+    pub update_interval: Option<Duration>,
+    /// Ask before sending a signal.
+    pub confirm_kill: bool,
+    /// Ask before quitting.
+    pub confirm_quit: bool,
+    /// Ports that are highlighted in the list.
+    pub watch_ports: Vec<u16>,
+    pub theme: ThemeName,
+    /// Replace the selection symbol and style of the theme.
+    pub highlight_symbol: Option<String>,
+    pub highlight_style: Option<Style>,
+    /// Show how long processes have been running. Off by default, without `/proc`
+    /// it runs `ps` for every process in every scan.
+    pub show_uptime: bool,
+    /// Count the established connections of each listener.
+    pub count_connections: bool,
+    /// Show service names next to ports, like `443/tcp (https)`.
+    pub service_names: bool,
+    /// Offer to retry with `sudo kill` when killing is not permitted.
+    pub sudo: bool,
+    /// Ask docker which container a Docker proxy publishes a port for.
+    pub docker: bool,
+    /// Never kill anything, for shared or production machines.
+    pub read_only: bool,
+    /// Only show processes with a port in this range.
+    pub min_port: Option<u16>,
+    pub max_port: Option<u16>,
+    /// The columns of the process list, in order.
+    pub columns: Vec<Column>,
+    /// The `[keys]` section, e.g. `kill = "dd"`.
+    pub keymap: Keymap,
+    /// What <enter> does in the process list, bound after the `[keys]` section.
+    on_enter: Option<Action>,
+    /// Values that were skipped, reported as a warning.
+    ignored: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_filter: String::new(),
+            kill_signal: Signal::default(),
+            update_interval: None,
+            confirm_kill: false,
+            confirm_quit: false,
+            watch_ports: Vec::new(),
+            theme: ThemeName::default(),
+            highlight_symbol: None,
+            highlight_style: None,
+            show_uptime: false,
+            count_connections: false,
+            service_names: true,
+            sudo: false,
+            docker: false,
+            read_only: false,
+            min_port: None,
+            max_port: None,
+            columns: Column::DEFAULT.to_vec(),
+            keymap: Keymap::default(),
+            on_enter: None,
+            ignored: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file. A missing file results in the default config.
+    /// Problems with the file are returned as a warning next to the config,
+    /// using defaults for everything that could not be read.
+    pub fn load() -> (Self, Option<String>) {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return (Self::default(), None);
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (Self::default(), None),
+            Err(e) => {
+                let warning = format!("Could not read {}: {e}", path.display());
+                return (Self::default(), Some(warning));
+            }
+        };
+
+        match Self::parse(&text) {
+            Ok(config) if !config.ignored.is_empty() => {
+                let warning = format!("{}: {}", path.display(), config.ignored.join(", "));
+                (config, Some(warning))
+            }
+            Ok(config) => (config, None),
+            Err(e) => {
+                // A broken config must not allow killing when it asked not to
+                let read_only = salvage_read_only(&text);
+                let except = if read_only { ", except read_only" } else { "" };
+                let warning = format!("Ignoring {}{except}: {e}", path.display());
+                let config = Self {
+                    read_only,
+                    ..Self::default()
+                };
+                (config, Some(warning))
+            }
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (key, value) in parse_toml(text)? {
+            config
+                .apply(&key, value)
+                .map_err(|e| format!("{key}: {e}"))?;
+        }
+        if let Some(action) = config.on_enter {
+            config.keymap.rebind("enter", action)?;
+        }
+        config.keymap.validate().map_err(|e| format!("keys: {e}"))?;
+        if let (Some(min), Some(max)) = (config.min_port, config.max_port)
+            && min > max
+        {
+            return Err(format!("min_port {min} is larger than max_port {max}"));
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match key {
+            "default_filter" => self.default_filter = value.into_string()?,
+            "kill_signal" => self.kill_signal = value.into_string()?.parse()?,
+            "update_interval_ms" => {
+                let ms = value.into_integer()?;
+                let ms = u64::try_from(ms).map_err(|_| "must not be negative".to_string())?;
+                if !INTERVAL_LIMITS.contains(&ms) {
+                    let (min, max) = (INTERVAL_LIMITS.start(), INTERVAL_LIMITS.end());
+                    return Err(format!("must be from {min} to {max}, not {ms}"));
+                }
+                self.update_interval = Some(Duration::from_millis(ms));
+            }
+            "confirm_kill" => self.confirm_kill = value.into_bool()?,
+            "confirm_quit" => self.confirm_quit = value.into_bool()?,
+            "watch_ports" => {
+                self.watch_ports = value
+                    .into_array()?
+                    .into_iter()
+                    .map(into_port)
+                    .collect::<Result<_, _>>()?;
+            }
+            "min_port" => self.min_port = Some(into_port(value)?),
+            "max_port" => self.max_port = Some(into_port(value)?),
+            "theme" => self.theme = value.into_string()?.parse()?,
+            "highlight_symbol" => {
+                let symbol = value.into_string()?;
+                // The rows are laid out around it
+                let width = Span::raw(&symbol).width();
+                if symbol.chars().any(char::is_control) || !(1..=3).contains(&width) {
+                    return Err("must be 1 to 3 columns wide".to_string());
+                }
+                self.highlight_symbol = Some(symbol);
+            }
+            "highlight_style" => self.highlight_style = Some(parse_style(&value.into_string()?)?),
+            "show_uptime" => self.show_uptime = value.into_bool()?,
+            "count_connections" => self.count_connections = value.into_bool()?,
+            "service_names" => self.service_names = value.into_bool()?,
+            "docker" => self.docker = value.into_bool()?,
+            "read_only" => self.read_only = value.into_bool()?,
+            "sudo" => self.sudo = value.into_bool()?,
+            "on_enter" => {
+                let action = match value.into_string()?.as_str() {
+                    "detail" => Action::Detail,
+                    "kill" => Action::Kill,
+                    "yank" => Action::YankPid,
+                    other => {
+                        return Err(format!(
+                            "unknown action `{other}`, use detail, kill or yank"
+                        ));
+                    }
+                };
+                self.on_enter = Some(action);
+            }
+            "columns" => {
+                self.columns.clear();
+                for name in value.into_array()? {
+                    match name.into_string()?.parse() {
+                        Ok(column) => self.columns.push(column),
+                        Err(e) => self.ignored.push(format!("ignoring {e}")),
+                    }
+                }
+            }
+            key if key.starts_with("keys.") => {
+                let action = key["keys.".len()..].parse()?;
+                // One sequence, or a list of them
+                let sequences = match value {
+                    Value::Array(items) => items
+                        .into_iter()
+                        .map(Value::into_string)
+                        .collect::<Result<_, _>>()?,
+                    value => vec![value.into_string()?],
+                };
+                self.keymap.bind(action, &sequences)?;
+            }
+            _ => return Err("unknown key".to_string()),
+        }
+        Ok(())
+    }
+}
+
+/// Whether a config that could not be parsed turns on read_only. Its line is looked at
+/// on its own, so errors elsewhere don't matter, and a value that is not `false` counts as on.
+fn salvage_read_only(text: &str) -> bool {
+    for line in text.lines().map(str::trim) {
+        // It is a top-level key, the sections come after those
+        if line.starts_with('[') {
+            break;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim().trim_matches('"') == "read_only" {
+            return !matches!(
+                parse_value(value.trim_start()),
+                Ok((Value::Boolean(false), _))
+            );
+        }
+    }
+    false
+}
+
+fn into_port(value: Value) -> Result<u16, String> {
+    let port = value.into_integer()?;
+    u16::try_from(port).map_err(|_| format!("invalid port {port}"))
+}
+
+/// The directory for portwitch's files, e.g. `~/.config/portwitch`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("portwitch"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn into_string(self) -> Result<String, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    pub fn into_integer(self) -> Result<i64, String> {
+        match self {
+            Value::Integer(i) => Ok(i),
+            _ => Err("expected an integer".to_string()),
+        }
+    }
+
+    pub fn into_bool(self) -> Result<bool, String> {
+        match self {
+            Value::Boolean(b) => Ok(b),
+            _ => Err("expected true or false".to_string()),
+        }
+    }
+
+    pub fn into_array(self) -> Result<Vec<Value>, String> {
+        match self {
+            Value::Array(a) => Ok(a),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+}
+
+/// Parse TOML into a list of keys and values.
+/// Keys inside a `[section]` are prefixed with the section name, e.g. `section.key`.
+pub fn parse_toml(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut section = String::new();
+    let mut entries = Vec::new();
+
+    let mut lines = text.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let line_error = |e: String| format!("line {}: {e}", number + 1);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[') {
+            let name = strip_comment(name).trim_end();
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| line_error("unclosed section header".to_string()))?;
+            section = format!("{}.", name.trim());
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| line_error("expected `key = value`".to_string()))?;
+        let key = key.trim().trim_matches('"');
+        let mut text = strip_comment(value).trim().to_string();
+        let value = loop {
+            match parse_value(&text) {
+                Ok((_, rest)) if !rest.trim().is_empty() => {
+                    return Err(line_error(format!("unexpected `{}`", rest.trim())));
+                }
+                Ok((value, _)) => break value,
+                // Arrays may go on over the following lines
+                Err(e) if e == UNCLOSED_ARRAY => {
+                    let (_, next) = lines.next().ok_or_else(|| line_error(e))?;
+                    text.push('\n');
+                    text.push_str(strip_comment(next));
+                }
+                Err(e) => return Err(line_error(e)),
+            }
+        };
+
+        entries.push((format!("{section}{key}"), value));
+    }
+
+    Ok(entries)
+}
+
+const UNCLOSED_ARRAY: &str = "unclosed array";
+
+/// Remove a trailing `# comment`, but not a `#` inside a string.
+fn strip_comment(s: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            // Only basic strings have escapes
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &s[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    s
+}
+
+/// Parse a value at the start of the text, returning it and the unparsed rest.
+fn parse_value(text: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        return parse_string(rest);
+    }
+
+    // Literal strings have no escapes
+    if let Some(rest) = text.strip_prefix('\'') {
+        let (value, rest) = rest
+            .split_once('\'')
+            .ok_or_else(|| "unclosed string".to_string())?;
+        return Ok((Value::String(value.to_string()), rest));
+    }
+
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return Err(UNCLOSED_ARRAY.to_string());
+            }
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), rest));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            rest = match rest.strip_prefix(',') {
+                Some(rest) => rest,
+                None if rest.is_empty() || rest.starts_with(']') => rest,
+                None => return Err("expected `,` or `]` in array".to_string()),
+            };
+        }
+    }
+
+    let end = text
+        .find(|c: char| c == ',' || c == ']' || c == '#' || c.is_whitespace())
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        "" => return Err("missing value".to_string()),
+        _ => Value::Integer(
+            word.replace('_', "")
+                .parse()
+                .map_err(|_| format!("invalid value `{word}`"))?,
+        ),
+    };
+    Ok((value, rest))
+}
+
+/// Parse the rest of a string after the opening quote.
+fn parse_string(text: &str) -> Result<(Value, &str), String> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((Value::String(value), &text[i + 1..])),
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('"' | '\\')) => value.push(c),
+                _ => return Err("invalid escape in string".to_string()),
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unclosed string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_keep_read_only() {
+        let text = "read_only = true\ntheme = \"nope\"\n";
+        assert!(Config::parse(text).is_err());
+        assert!(salvage_read_only(text));
+        // Even if the file is not valid TOML
+        assert!(salvage_read_only(
+            "confirm_kill = \nread_only = true # safety\n"
+        ));
+        // Something else than false is taken as on
+        assert!(salvage_read_only("read_only = yes\n"));
+        assert!(!salvage_read_only("read_only = false\nunknown = 1\n"));
+        assert!(!salvage_read_only("unknown = 1\n"));
+        // Not the top-level key
+        assert!(!salvage_read_only("[keys]\nread_only = \"r\"\n"));
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn values() {
+        let text = r#"
+            # A comment
+            name = "portwitch"
+            literal = 'C:\temp'
+            escaped = "a \"b\"\tc\\"
+            number = 1_000 # A trailing comment
+            negative = -5
+            yes = true
+            ports = [3000, 8080,]
+            "quoted" = false
+        "#;
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            vec![
+                ("name".to_string(), string("portwitch")),
+                ("literal".to_string(), string("C:\\temp")),
+                ("escaped".to_string(), string("a \"b\"\tc\\")),
+                ("number".to_string(), Value::Integer(1000)),
+                ("negative".to_string(), Value::Integer(-5)),
+                ("yes".to_string(), Value::Boolean(true)),
+                (
+                    "ports".to_string(),
+                    Value::Array(vec![Value::Integer(3000), Value::Integer(8080)])
+                ),
+                ("quoted".to_string(), Value::Boolean(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let text = "top = 1\n[keys] # Key bindings\nkill = \"x\"\n[ theme ]\nmatched = \"red\"\n";
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            vec![
+                ("top".to_string(), Value::Integer(1)),
+                ("keys.kill".to_string(), string("x")),
+                ("theme.matched".to_string(), string("red")),
+            ]
+        );
+    }
+
+    #[test]
+    fn hash_in_strings() {
+        let text = "highlight_style = \"#ff8000\" # orange\nsymbol = '#' \nquote = \"\\\"#\\\\\" # \"#\"\n";
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            vec![
+                ("highlight_style".to_string(), string("#ff8000")),
+                ("symbol".to_string(), string("#")),
+                ("quote".to_string(), string("\"#\\")),
+            ]
+        );
+        assert_eq!(strip_comment("a = 1 # b"), "a = 1 ");
+        assert_eq!(strip_comment("a = \"#\" # b"), "a = \"#\" ");
+        assert_eq!(strip_comment("a = '\\' # b"), "a = '\\' ");
+    }
+
+    #[test]
+    fn multi_line_arrays() {
+        let text = r##"
+            ports = [
+                3000, # Frontend
+                8080,
+                [1, 2],
+            ]
+            after = "#"
+            empty = [
+            ]
+        "##;
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            vec![
+                (
+                    "ports".to_string(),
+                    Value::Array(vec![
+                        Value::Integer(3000),
+                        Value::Integer(8080),
+                        Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+                    ])
+                ),
+                ("after".to_string(), string("#")),
+                ("empty".to_string(), Value::Array(Vec::new())),
+            ]
+        );
+        assert_eq!(
+            parse_toml("ports = [\n1,\n").unwrap_err(),
+            "line 1: unclosed array"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text| parse_toml(text).unwrap_err();
+        assert_eq!(error("a = 1\nkey"), "line 2: expected `key = value`");
+        assert_eq!(error("[keys"), "line 1: unclosed section header");
+        assert_eq!(error("a = \"b"), "line 1: unclosed string");
+        assert_eq!(error("a = 'b"), "line 1: unclosed string");
+        assert_eq!(error("a = \"\\x\""), "line 1: invalid escape in string");
+        assert_eq!(error("a = 1 2"), "line 1: unexpected `2`");
+        assert_eq!(error("a = [1 2]"), "line 1: expected `,` or `]` in array");
+        assert_eq!(error("a ="), "line 1: missing value");
+        assert_eq!(error("a = yes"), "line 1: invalid value `yes`");
+    }
+}
@@ -0,0 +1,101 @@
+//! Find the Docker container behind a port that a Docker proxy listens on.
+
+use std::io;
+use std::process::Command;
+
+/// A running container and the host ports it publishes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Container {
+    pub name: String,
+    pub ports: Vec<u16>,
+}
+
+/// Whether the command is one of the processes that Docker uses to publish ports.
+/// lsof cuts command names after nine characters, so only the start is compared.
+pub fn is_proxy(command: &str) -> bool {
+    ["docker-pr", "com.docke", "vpnkit", "rootlessk", "rootlessp"]
+        .iter()
+        .any(|prefix| command.starts_with(prefix))
+}
+
+/// Ask the docker CLI for the running containers.
+pub fn containers() -> io::Result<Vec<Container>> {
+    let output = Command::new("docker")
+        .args(["ps", "--format", "{{.Names}}\t{{.Ports}}"])
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "docker not found"),
+            _ => io::Error::new(e.kind(), format!("failed to run docker: {e}")),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(parse_ps_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse lines like `web\t0.0.0.0:8080->80/tcp, [::]:8080->80/tcp, 443/tcp`.
+/// Ports without `->` are exposed but not published, so nothing on the host listens on them.
+fn parse_ps_output(out: &str) -> Vec<Container> {
+    out.lines()
+        .filter_map(|line| {
+            let (name, ports) = line.split_once('\t')?;
+            let mut published = Vec::new();
+            for mapping in ports.split(", ") {
+                let Some((host, _container)) = mapping.split_once("->") else {
+                    continue;
+                };
+                let Some((_address, range)) = host.rsplit_once(':') else {
+                    continue;
+                };
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                let (Ok(start), Ok(end)) = (start.parse::<u16>(), end.parse::<u16>()) else {
+                    continue;
+                };
+                for port in start..=end {
+                    if !published.contains(&port) {
+                        published.push(port);
+                    }
+                }
+            }
+            Some(Container {
+                name: name.to_string(),
+                ports: published,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ps_output() {
+        let out = "web\t0.0.0.0:8080->80/tcp, [::]:8080->80/tcp, 443/tcp\n\
+                   db\t127.0.0.1:5432->5432/tcp\n\
+                   workers\t0.0.0.0:9000-9002->9000-9002/tcp\n\
+                   idle\t\n";
+        let container = |name: &str, ports: &[u16]| Container {
+            name: name.to_string(),
+            ports: ports.to_vec(),
+        };
+        assert_eq!(
+            parse_ps_output(out),
+            vec![
+                container("web", &[8080]),
+                container("db", &[5432]),
+                container("workers", &[9000, 9001, 9002]),
+                container("idle", &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn proxies() {
+        assert!(is_proxy("docker-proxy"));
+        assert!(is_proxy("com.docke"));
+        assert!(!is_proxy("dockerd"));
+        assert!(!is_proxy("nginx"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Print a line of JSON whenever a port opens or closes, without the TUI.
+
+use crate::export;
+use portwitch::{PortSource, Process, ScanOptions, Socket, processes};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+use strum::Display;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Event {
+    Open,
+    Close,
+}
+
+/// A socket together with the process it belongs to.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Entry {
+    pid: usize,
+    command: String,
+    socket: Socket,
+}
+
+/// Scan every interval and print what changed, until interrupted or stdout is closed.
+/// The sockets of the first scan are printed as opened, so the reader knows them all.
+pub fn watch(source: &dyn PortSource, options: ScanOptions, interval: Duration) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let mut previous = Vec::new();
+    loop {
+        match processes(source, options) {
+            Ok(scan) => {
+                let current = entries(&scan.processes);
+                for (event, entry) in changes(&previous, &current) {
+                    let line = export::event(event, entry.pid, &entry.command, &entry.socket);
+                    if let Err(e) = writeln!(out, "{line}") {
+                        // The reader is gone, like `head` after enough lines
+                        return match e.kind() {
+                            io::ErrorKind::BrokenPipe => Ok(()),
+                            _ => Err(e),
+                        };
+                    }
+                }
+                previous = current;
+            }
+            // Keep watching, the next scan may work again
+            Err(e) => eprintln!("portwitch: {e}"),
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn entries(processes: &[Process]) -> Vec<Entry> {
+    processes
+        .iter()
+        .flat_map(|p| {
+            p.ports.iter().map(|socket| Entry {
+                pid: p.pid,
+                command: p.command.clone(),
+                socket: socket.clone(),
+            })
+        })
+        .collect()
+}
+
+/// The sockets that closed since the previous scan, then the ones that opened.
+fn changes<'a>(previous: &'a [Entry], current: &'a [Entry]) -> Vec<(Event, &'a Entry)> {
+    let before: HashSet<&Entry> = previous.iter().collect();
+    let now: HashSet<&Entry> = current.iter().collect();
+    let closed = previous.iter().filter(|e| !now.contains(e));
+    let opened = current.iter().filter(|e| !before.contains(e));
+    closed
+        .map(|e| (Event::Close, e))
+        .chain(opened.map(|e| (Event::Open, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portwitch::test_support::socket;
+
+    fn entry(pid: usize, port: u16) -> Entry {
+        Entry {
+            pid,
+            command: "node".to_string(),
+            socket: socket(port),
+        }
+    }
+
+    #[test]
+    fn opened_and_closed() {
+        let previous = [entry(1, 3000), entry(1, 3001), entry(2, 8080)];
+        let mut moved = entry(1, 3001);
+        moved.socket.fd = Some("9".to_string());
+        let current = [entry(1, 3000), moved, entry(3, 8080)];
+
+        let events = changes(&previous, &current)
+            .into_iter()
+            .map(|(event, e)| (event, e.pid, e.socket.port))
+            .collect::<Vec<_>>();
+        // Another descriptor of the same socket is no change
+        assert_eq!(events, [(Event::Close, 2, 8080), (Event::Open, 3, 8080)]);
+        assert_eq!(changes(&[], &previous).len(), 3);
+        assert_eq!(
+            export::event(Event::Open, 1, "node", &previous[0].socket),
+            r#"{"event":"open","pid":1,"command":"node","protocol":"tcp","family":"ipv4","address":"*","port":3000}"#
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Machine readable output of the process list.
+
+use crate::events::Event;
+use itertools::Itertools;
+use portwitch::{Process, Socket};
+
+/// An array of objects like `{"pid":1,"command":"node","ports":[...]}`.
+pub fn json(processes: &[Process]) -> String {
+    let mut records = processes.iter().map(|p| {
+        let mut ports = p.ports.iter().map(|s| format!("{{{}}}", socket_fields(s)));
+        format!(
+            r#"{{"pid":{},"command":{},"ports":[{}]}}"#,
+            p.pid,
+            json_string(&p.command),
+            ports.join(",")
+        )
+    });
+    format!("[{}]", records.join(","))
+}
+
+/// One line like `{"event":"open","pid":1,"command":"node","protocol":"tcp",...}`.
+pub fn event(event: Event, pid: usize, command: &str, socket: &Socket) -> String {
+    format!(
+        r#"{{"event":"{event}","pid":{pid},"command":{},{}}}"#,
+        json_string(command),
+        socket_fields(socket)
+    )
+}
+
+/// The fields of a socket in an object, without the braces.
+fn socket_fields(s: &Socket) -> String {
+    let remote = match &s.remote {
+        Some(remote) => format!(r#","remote":{}"#, json_string(remote)),
+        None => String::new(),
+    };
+    format!(
+        r#""protocol":"{}","family":"{}","address":{},"port":{}{remote}"#,
+        s.protocol,
+        s.family,
+        json_string(&s.address),
+        s.port
+    )
+}
+
+/// A header line and one line per process, with ports like `*:80/tcp` or `[::1]:80/tcp6` joined by semicolons.
+pub fn csv(processes: &[Process]) -> String {
+    let mut out = String::from("pid,command,ports\n");
+    for p in processes {
+        let ports = p
+            .ports
+            .iter()
+            .map(|s| match &s.remote {
+                Some(remote) => format!("{}->{remote}/{}", s.network(), s.kind()),
+                None => format!("{}/{}", s.network(), s.kind()),
+            })
+            .join(";");
+        out.push_str(&format!(
+            "{},{},{}\n",
+            p.pid,
+            csv_field(&p.command),
+            csv_field(&ports)
+        ));
+    }
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\t' => out.push_str(r"\t"),
+            c if c.is_control() => out.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quote a field if it contains characters that have a meaning in CSV.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portwitch::Family;
+    use portwitch::test_support::{process, socket};
+
+    fn connected(command: &str) -> Process {
+        let mut process = process(1, command, 80);
+        process.ports[0] = Socket {
+            family: Family::V6,
+            address: "::1".to_string(),
+            remote: Some("[::1]:51234".to_string()),
+            ..socket(80)
+        };
+        process
+    }
+
+    #[test]
+    fn json_escapes() {
+        assert_eq!(json_string("node"), r#""node""#);
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"C:\node"), r#""C:\\node""#);
+        assert_eq!(json_string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(
+            json_string("\0\u{1b}[0m\u{7f}"),
+            r#""\u0000\u001b[0m\u007f""#
+        );
+        // Only control characters are escaped, JSON is UTF-8
+        assert_eq!(json_string("café ⟳ 日本"), r#""café ⟳ 日本""#);
+        assert_eq!(
+            json(&[connected("a\"b")]),
+            r#"[{"pid":1,"command":"a\"b","ports":[{"protocol":"tcp","family":"ipv6","address":"::1","port":80,"remote":"[::1]:51234"}]}]"#
+        );
+        assert_eq!(json(&[]), "[]");
+    }
+
+    #[test]
+    fn csv_quotes() {
+        assert_eq!(csv_field("node"), "node");
+        assert_eq!(csv_field("a,b"), r#""a,b""#);
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+        assert_eq!(csv_field("café ⟳"), "café ⟳");
+        assert_eq!(
+            csv(&[connected("node, \"dev\"")]),
+            "pid,command,ports\n1,\"node, \"\"dev\"\"\",[::1]:80->[::1]:51234/tcp6\n"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use itertools::Itertools;
+use portwitch::Process;
+use portwitch::procinfo;
+use regex::Regex;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// How the filter text is matched against the command of a process.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FilterMode {
+    /// Case-insensitive substring match.
+    #[default]
+    Substring,
+    /// Case-insensitive subsequence match, e.g. `dkr` matches `docker`.
+    Fuzzy,
+    /// The filter is a regular expression matched against command, addresses and PID.
+    Regex,
+}
+
+impl FilterMode {
+    /// The next mode when cycling through the filter modes.
+    pub fn next(self) -> Self {
+        Self::iter()
+            .cycle()
+            .skip_while(|&m| m != self)
+            .nth(1)
+            .unwrap()
+    }
+}
+
+impl FromStr for FilterMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterMode::iter()
+            .find(|mode| mode.to_string() == s)
+            .ok_or_else(|| format!("unknown filter mode `{s}`"))
+    }
+}
+
+/// A filter ready to be matched against processes.
+///
+/// Outside of regex mode, every word must match: `node !test` finds node processes
+/// without `test`, and `8080|8443` finds processes on either port.
+/// Words like `:80`, `3000-3010` or `>1024` match ports precisely. Other words
+/// are matched as text. Numbers are only matched against ports and PIDs, so `1`
+/// does not find every `127.0.0.1`, and `@127.0` searches the addresses.
+/// A prefix like `cmd:node`, `port:>3000`, `pid:42`, `user:root` or `addr:127.0`
+/// matches only that field.
+pub struct Filter {
+    mode: FilterMode,
+    /// Only set in regex mode, if the text is a valid regex.
+    regex: Option<Regex>,
+    /// The text in regex mode, used as a substring if the regex does not compile.
+    text: String,
+    /// The alternatives of each word.
+    words: Vec<Vec<Term>>,
+}
+
+/// One alternative of a word, like `!test` or `:80`.
+struct Term {
+    negated: bool,
+    matcher: Matcher,
+}
+
+enum Matcher {
+    Ports(RangeInclusive<u16>),
+    Pid(usize),
+    User(u32),
+    Command(String),
+    Address(String),
+    /// Any field that is not a port.
+    Text(String),
+    /// A field with a value it can never have, like `pid:abc`.
+    Nothing,
+}
+
+impl Filter {
+    pub fn new(text: &str, mode: FilterMode) -> Self {
+        let words = match mode {
+            FilterMode::Regex => Vec::new(),
+            _ => text
+                .split_whitespace()
+                .map(|word| word.split('|').filter_map(parse_term).collect_vec())
+                .filter(|terms| !terms.is_empty())
+                .collect(),
+        };
+        Self {
+            mode,
+            regex: match mode {
+                FilterMode::Regex => Regex::new(text).ok(),
+                _ => None,
+            },
+            text: text.to_string(),
+            words,
+        }
+    }
+
+    /// A regex filter that does not compile. It falls back to substring matching.
+    pub fn is_invalid(&self) -> bool {
+        self.mode == FilterMode::Regex && self.regex.is_none()
+    }
+
+    pub fn matches(&self, p: &Process) -> bool {
+        if let Some(regex) = &self.regex {
+            return regex.is_match(&p.command)
+                || p.ports.iter().any(|port| regex.is_match(&port.network()))
+                || regex.is_match(&p.pid.to_string());
+        }
+        if self.mode == FilterMode::Regex {
+            return matches_text(p, &self.text, FilterMode::Substring);
+        }
+
+        self.words.iter().all(|terms| {
+            terms.iter().any(|term| {
+                let matches = match &term.matcher {
+                    Matcher::Ports(range) => p.ports.iter().any(|s| range.contains(&s.port)),
+                    Matcher::Pid(pid) => p.pid == *pid,
+                    Matcher::User(uid) => p.uid == Some(*uid),
+                    Matcher::Command(text) => matches_command(&p.command, text, self.mode),
+                    Matcher::Address(host) => matches_address(p, host),
+                    Matcher::Text(text) => matches_text(p, text, self.mode),
+                    Matcher::Nothing => false,
+                };
+                matches != term.negated
+            })
+        })
+    }
+}
+
+/// How well a fuzzy filter matches a command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i64,
+    /// The matched characters of the command, as char indices.
+    pub positions: Vec<usize>,
+}
+
+impl Filter {
+    /// Rank the command against the words of a fuzzy filter. Of each word, the best matching
+    /// alternative counts. `None` outside of fuzzy mode, or if no word matches the command,
+    /// like when the filter only has ports.
+    pub fn fuzzy_match(&self, command: &str) -> Option<FuzzyMatch> {
+        if self.mode != FilterMode::Fuzzy {
+            return None;
+        }
+        let mut found: Option<FuzzyMatch> = None;
+        for terms in &self.words {
+            let best = terms
+                .iter()
+                .filter(|term| !term.negated)
+                .filter_map(|term| match &term.matcher {
+                    Matcher::Command(text) => Some(text),
+                    // Numbers and addresses are not matched against the command
+                    Matcher::Text(text)
+                        if !text.starts_with('@') && !text.chars().all(|c| c.is_ascii_digit()) =>
+                    {
+                        Some(text)
+                    }
+                    _ => None,
+                })
+                .filter_map(|text| fuzzy_score(text, command))
+                .max_by_key(|m| m.score);
+            if let Some(best) = best {
+                let total = found.get_or_insert(FuzzyMatch {
+                    score: 0,
+                    positions: Vec::new(),
+                });
+                total.score += best.score;
+                total.positions.extend(best.positions);
+            }
+        }
+        if let Some(found) = &mut found {
+            found.positions.sort_unstable();
+            found.positions.dedup();
+        }
+        found
+    }
+}
+
+/// Parse one alternative of a word. A lone `!` is taken as text.
+fn parse_term(term: &str) -> Option<Term> {
+    if term.is_empty() {
+        return None;
+    }
+    let (negated, pattern) = match term.strip_prefix('!') {
+        Some(pattern) if !pattern.is_empty() => (true, pattern),
+        _ => (false, term),
+    };
+    let matcher = match (parse_field(pattern), parse_port_range(pattern)) {
+        (Some(matcher), _) => matcher,
+        (None, Some(range)) => Matcher::Ports(range),
+        (None, None) => Matcher::Text(pattern.to_string()),
+    };
+    Some(Term { negated, matcher })
+}
+
+/// Parse a word with a field prefix like `cmd:node`. `None` for other prefixes,
+/// so `*:80` is still text.
+fn parse_field(word: &str) -> Option<Matcher> {
+    let (field, value) = word.split_once(':')?;
+    let matcher = match field.to_lowercase().as_str() {
+        "cmd" | "command" => Matcher::Command(value.to_string()),
+        "addr" | "address" => Matcher::Address(value.to_string()),
+        "port" => {
+            match parse_port_range(value).or_else(|| parse_port_range(&format!(":{value}"))) {
+                Some(range) => Matcher::Ports(range),
+                None => Matcher::Nothing,
+            }
+        }
+        "pid" => value.parse().map_or(Matcher::Nothing, Matcher::Pid),
+        "user" => match value.parse().ok().or_else(|| procinfo::user_id(value)) {
+            Some(uid) => Matcher::User(uid),
+            None => Matcher::Nothing,
+        },
+        _ => return None,
+    };
+    Some(matcher)
+}
+
+fn matches_address(p: &Process, host: &str) -> bool {
+    p.ports
+        .iter()
+        .any(|s| s.address.contains(host) || s.remote.as_ref().is_some_and(|r| r.contains(host)))
+}
+
+fn matches_text(p: &Process, text: &str, mode: FilterMode) -> bool {
+    if let Some(host) = text.strip_prefix('@') {
+        return matches_address(p, host);
+    }
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        return p.ports.iter().any(|s| s.port.to_string().contains(text))
+            || p.pid.to_string().contains(text);
+    }
+
+    matches_command(&p.command, text, mode)
+        || p.ports
+            .iter()
+            .any(|port| port.to_string().contains(text) || port.network().contains(text))
+}
+
+/// Parse `:80`, `3000-3010`, `>1024`, `>=1024`, `<1024` or `<=1024` into the ports they match.
+fn parse_port_range(word: &str) -> Option<RangeInclusive<u16>> {
+    let port = |s: &str| s.parse::<u16>().ok();
+    if let Some(p) = word.strip_prefix(':') {
+        let p = port(p)?;
+        return Some(p..=p);
+    }
+    if let Some(p) = word.strip_prefix(">=") {
+        return Some(port(p)?..=u16::MAX);
+    }
+    if let Some(p) = word.strip_prefix("<=") {
+        return Some(0..=port(p)?);
+    }
+    if let Some(p) = word.strip_prefix('>') {
+        return Some(port(p)?.checked_add(1)?..=u16::MAX);
+    }
+    if let Some(p) = word.strip_prefix('<') {
+        return Some(0..=port(p)?.checked_sub(1)?);
+    }
+    let (start, end) = word.split_once('-')?;
+    Some(port(start)?..=port(end)?)
+}
+
+fn matches_command(command: &str, filter: &str, mode: FilterMode) -> bool {
+    let command = command.to_lowercase();
+    let filter = filter.to_lowercase();
+    match mode {
+        FilterMode::Fuzzy => is_subsequence(&filter, &command),
+        FilterMode::Substring | FilterMode::Regex => command.contains(&filter),
+    }
+}
+
+/// Check if all characters of `needle` appear in `haystack` in the same order.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|n| haystack.any(|h| h == n))
+}
+
+/// Score `needle` as a case-insensitive subsequence of `haystack`, trying every start.
+/// Characters following each other or starting a word score more, gaps cost.
+fn fuzzy_score(needle: &str, haystack: &str) -> Option<FuzzyMatch> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let needle = needle.chars().map(fold).collect_vec();
+    let haystack = haystack.chars().collect_vec();
+    let first = *needle.first()?;
+
+    let mut best: Option<FuzzyMatch> = None;
+    for start in (0..haystack.len()).filter(|&i| fold(haystack[i]) == first) {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for &n in &needle[1..] {
+            let offset = haystack[next..].iter().position(|&h| fold(h) == n);
+            let Some(offset) = offset else {
+                // Later starts cannot find it either
+                return best;
+            };
+            positions.push(next + offset);
+            next += offset + 1;
+        }
+
+        // Where the match starts matters less than how tight it is
+        let mut score = -(start.min(15) as i64);
+        for (i, &p) in positions.iter().enumerate() {
+            score += 16;
+            if p == 0 || !haystack[p - 1].is_alphanumeric() {
+                score += 8;
+            }
+            if i > 0 {
+                let gap = p - positions[i - 1] - 1;
+                score += if gap == 0 { 8 } else { -(gap as i64) };
+            }
+        }
+        if best.as_ref().is_none_or(|b| score > b.score) {
+            best = Some(FuzzyMatch { score, positions });
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portwitch::test_support::process;
+    use portwitch::{Protocol, Socket};
+
+    fn listener(local: &str) -> Process {
+        Process {
+            ports: vec![Socket::parse_local(Protocol::Tcp, local).unwrap()],
+            ..process(42, "nginx", 0)
+        }
+    }
+
+    #[test]
+    fn matches_ipv6_ports() {
+        let process = listener("[::1]:8080");
+        for text in ["8080", "::1", "[::1]:8080", "tcp6"] {
+            assert!(
+                Filter::new(text, FilterMode::Substring).matches(&process),
+                "{text}"
+            );
+        }
+        assert!(Filter::new(r"^\[::1\]:8080$", FilterMode::Regex).matches(&process));
+        assert!(!Filter::new("8081", FilterMode::Substring).matches(&process));
+    }
+
+    #[test]
+    fn matches_ipv6_wildcard() {
+        let process = listener("[::]:80");
+        assert!(Filter::new("80", FilterMode::Substring).matches(&process));
+        assert!(Filter::new("*:80", FilterMode::Substring).matches(&process));
+    }
+
+    #[test]
+    fn numbers_match_ports_not_addresses() {
+        let process = listener("127.0.0.1:3000");
+        assert!(Filter::new("300", FilterMode::Substring).matches(&process));
+        assert!(Filter::new("42", FilterMode::Substring).matches(&process));
+        assert!(!Filter::new("1", FilterMode::Substring).matches(&process));
+        assert!(!Filter::new("127", FilterMode::Substring).matches(&process));
+        assert!(Filter::new("@127", FilterMode::Substring).matches(&process));
+        assert!(Filter::new("127.0.0.1", FilterMode::Substring).matches(&process));
+        assert!(!Filter::new("@10.0", FilterMode::Substring).matches(&process));
+    }
+
+    #[test]
+    fn port_ranges() {
+        assert_eq!(parse_port_range(":80"), Some(80..=80));
+        assert_eq!(parse_port_range("3000-3010"), Some(3000..=3010));
+        assert_eq!(parse_port_range(">1024"), Some(1025..=u16::MAX));
+        assert_eq!(parse_port_range(">=1024"), Some(1024..=u16::MAX));
+        assert_eq!(parse_port_range("<1024"), Some(0..=1023));
+        assert_eq!(parse_port_range("<=1024"), Some(0..=1024));
+        for word in ["80", "::1", ":http", "a-b", ">65535", "<0", "3000-"] {
+            assert_eq!(parse_port_range(word), None, "{word}");
+        }
+    }
+
+    #[test]
+    fn exact_port() {
+        let filter = Filter::new(":80", FilterMode::Substring);
+        assert!(filter.matches(&listener("*:80")));
+        assert!(!filter.matches(&listener("*:8080")));
+        assert!(!filter.matches(&listener("*:18080")));
+    }
+
+    #[test]
+    fn ports_and_text() {
+        let filter = Filter::new("nginx >1024", FilterMode::Substring);
+        assert!(filter.matches(&listener("*:8080")));
+        assert!(!filter.matches(&listener("*:80")));
+        assert!(!Filter::new("node >1024", FilterMode::Substring).matches(&listener("*:8080")));
+        // Not in regex mode
+        assert!(!Filter::new("3000-3010", FilterMode::Regex).matches(&listener("*:3005")));
+    }
+
+    #[test]
+    fn combined_words() {
+        let matches = |text: &str, local: &str| {
+            Filter::new(text, FilterMode::Substring).matches(&listener(local))
+        };
+        assert!(matches("nginx !test", "*:80"));
+        assert!(!matches("nginx !nginx", "*:80"));
+        assert!(matches("8080|8443", "*:8443"));
+        assert!(!matches("8080|8443", "*:9000"));
+        assert!(matches("node|nginx :80", "*:80"));
+        assert!(!matches("node|nginx :80", "*:81"));
+        assert!(matches("!<1024", "*:3000"));
+        assert!(!matches("!<1024", "*:22"));
+        // A lone `!` is searched as text, an empty alternative is skipped
+        assert!(!matches("!", "*:80"));
+        assert!(matches("nginx |", "*:80"));
+    }
+
+    #[test]
+    fn fields() {
+        let matches = |text: &str, local: &str| {
+            let mut process = listener(local);
+            process.uid = Some(0);
+            Filter::new(text, FilterMode::Substring).matches(&process)
+        };
+        assert!(matches("cmd:ngi port:>3000", "*:8080"));
+        assert!(!matches("cmd:ngi port:>3000", "*:80"));
+        assert!(matches("port:80", "*:80"));
+        assert!(!matches("port:80", "*:8080"));
+        // 42 is not in the command, nor 80 in the PID
+        assert!(!matches("cmd:42", "*:80"));
+        assert!(matches("pid:42", "*:80"));
+        assert!(!matches("pid:80", "*:80"));
+        assert!(!matches("pid:nginx", "*:80"));
+        assert!(matches("user:0", "*:80"));
+        assert!(!matches("!user:0", "*:80"));
+        assert!(matches("addr:127.0 CMD:nginx", "127.0.0.1:80"));
+        assert!(!matches("addr:127.0", "*:80"));
+        // Unknown fields are text
+        assert!(matches("*:80", "*:80"));
+        assert!(!matches("color:red", "*:80"));
+    }
+
+    #[test]
+    fn fuzzy_scores() {
+        let m = fuzzy_score("dkr", "docker").unwrap();
+        assert_eq!(m.positions, [0, 3, 5]);
+        assert_eq!(fuzzy_score("DKR", "Docker").unwrap().positions, [0, 3, 5]);
+        assert_eq!(fuzzy_score("xyz", "docker"), None);
+        // The tight match at the start of a word wins over the first one
+        assert_eq!(fuzzy_score("ng", "xnyg nginx").unwrap().positions, [5, 6]);
+        let score = |needle: &str, haystack: &str| fuzzy_score(needle, haystack).unwrap().score;
+        assert!(score("ng", "nginx") > score("ng", "mongod"));
+        assert!(score("node", "node") > score("node", "n-o-d-e"));
+    }
+
+    #[test]
+    fn fuzzy_match_words() {
+        let filter = Filter::new("ngx !test 8080", FilterMode::Fuzzy);
+        let m = filter.fuzzy_match("nginx").unwrap();
+        assert_eq!(m.positions, [0, 1, 4]);
+        assert_eq!(filter.fuzzy_match("node"), None);
+        // Only the words that match the command count
+        assert_eq!(
+            Filter::new(":80", FilterMode::Fuzzy).fuzzy_match("nginx"),
+            None
+        );
+        assert_eq!(
+            Filter::new("ngx", FilterMode::Substring).fuzzy_match("nginx"),
+            None
+        );
+        let either = Filter::new("node|ngx cmd:ginx", FilterMode::Fuzzy);
+        assert_eq!(
+            either.fuzzy_match("nginx").unwrap().positions,
+            [0, 1, 2, 3, 4]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Filters that were used before, kept in `~/.config/portwitch/history`.
+
+use crate::config::config_dir;
+use std::path::PathBuf;
+use std::{fs, io};
+
+/// How many filters are remembered.
+const MAX_ENTRIES: usize = 50;
+
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest first.
+    entries: Vec<String>,
+    /// The entry that is shown while browsing, `None` when not browsing.
+    position: Option<usize>,
+    /// What was typed before browsing started, shown again after the newest entry.
+    draft: String,
+    /// Where the history is saved. Without a path it is only kept in memory.
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Load the history file. A missing or unreadable file is an empty history.
+    pub fn load() -> Self {
+        let path = config_dir().map(|dir| dir.join("history"));
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        Self {
+            entries,
+            path,
+            ..Self::default()
+        }
+    }
+
+    /// Remember a filter that was applied and save the history.
+    pub fn push(&mut self, filter: &str) -> io::Result<()> {
+        self.reset();
+        if filter.trim().is_empty() {
+            return Ok(());
+        }
+        self.entries.retain(|entry| entry != filter);
+        self.entries.push(filter.to_string());
+        let too_many = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..too_many);
+        self.save()
+    }
+
+    /// Stop browsing.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// The next older entry. `current` is the text being edited, which is kept
+    /// to come back to.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(position) => position.saturating_sub(1),
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The next newer entry, or the text from before browsing after the newest one.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(&self.entries[position])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = self.entries.join("\n");
+        text.push('\n');
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        History {
+            entries: entries.iter().map(|e| e.to_string()).collect(),
+            ..History::default()
+        }
+    }
+
+    #[test]
+    fn browse() {
+        let mut history = history(&["node", "8080"]);
+        assert_eq!(history.previous("ng"), Some("8080"));
+        assert_eq!(history.previous("8080"), Some("node"));
+        assert_eq!(history.previous("node"), Some("node"));
+        assert_eq!(history.next(), Some("8080"));
+        assert_eq!(history.next(), Some("ng"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn empty() {
+        let mut history = history(&[]);
+        assert_eq!(history.previous("x"), None);
+        assert_eq!(history.next(), None);
+    }
+}
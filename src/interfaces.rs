@@ -0,0 +1,66 @@
+//! The addresses of a network interface, like `192.168.1.10` for `eth0`.
+
+use std::io;
+use std::process::Command;
+
+/// Ask `ip` on Linux or `ifconfig` elsewhere for the addresses of the interface.
+pub fn addresses(interface: &str) -> io::Result<Vec<String>> {
+    #[cfg(target_os = "linux")]
+    let (program, args) = ("ip", ["-o", "addr", "show", "dev", interface]);
+    #[cfg(not(target_os = "linux"))]
+    let (program, args) = ("ifconfig", [interface]);
+
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("{program} not found")),
+            _ => io::Error::new(e.kind(), format!("failed to run {program}: {e}")),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(parse_addresses(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Both `ip -o addr` and `ifconfig` print `inet 192.168.1.10/24` or `inet6 fe80::1%en0`,
+/// with a prefix length or a zone that is not part of the address.
+fn parse_addresses(out: &str) -> Vec<String> {
+    let mut addresses = Vec::new();
+    for (word, next) in out.split_whitespace().zip(out.split_whitespace().skip(1)) {
+        if word != "inet" && word != "inet6" {
+            continue;
+        }
+        let address = next.split(['/', '%']).next().unwrap_or(next);
+        if !addresses.iter().any(|a| a == address) {
+            addresses.push(address.to_string());
+        }
+    }
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_output() {
+        let out = "2: eth0    inet 192.168.1.10/24 brd 192.168.1.255 scope global eth0\\       valid_lft forever preferred_lft forever\n\
+                   2: eth0    inet6 fe80::a00:27ff:fe4e:66a1/64 scope link \\       valid_lft forever preferred_lft forever\n";
+        assert_eq!(
+            parse_addresses(out),
+            ["192.168.1.10", "fe80::a00:27ff:fe4e:66a1"]
+        );
+    }
+
+    #[test]
+    fn ifconfig_output() {
+        let out = "en0: flags=8863<UP,BROADCAST,SMART,RUNNING,SIMPLEX,MULTICAST> mtu 1500\n\
+                   \tether 3c:22:fb:00:00:01\n\
+                   \tinet6 fe80::1c2b:3f:1e:9a1%en0 prefixlen 64 secured scopeid 0x6\n\
+                   \tinet 10.0.0.7 netmask 0xffffff00 broadcast 10.0.0.255\n\
+                   \tstatus: active\n";
+        assert_eq!(parse_addresses(out), ["fe80::1c2b:3f:1e:9a1", "10.0.0.7"]);
+    }
+}
@@ -0,0 +1,379 @@
+//! Key bindings of the process list, which can be changed in the `[keys]` section of the config.
+
+use itertools::Itertools;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::str::FromStr;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Something a key in the process list can do, named like `kill` or `select_next` in the config.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    SelectPrevious,
+    SelectNext,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    SelectFirst,
+    SelectLast,
+    Quit,
+    Help,
+    Filter,
+    Command,
+    Sort,
+    ReverseSort,
+    FilterMode,
+    Connections,
+    Pause,
+    PublicOnly,
+    Mine,
+    Tree,
+    SlowerUpdates,
+    FasterUpdates,
+    Refresh,
+    Kill,
+    ForceKill,
+    KillGroup,
+    PickSignal,
+    Restart,
+    Detail,
+    Mark,
+    OpenFiles,
+    KillLog,
+    ServiceNames,
+    Compact,
+    YankPid,
+    YankPorts,
+    YankKill,
+    OpenBrowser,
+}
+
+impl Action {
+    /// Actions that kill processes or prepare for it, which read-only mode disables.
+    pub fn kills(self) -> bool {
+        matches!(
+            self,
+            Action::Kill
+                | Action::ForceKill
+                | Action::KillGroup
+                | Action::PickSignal
+                | Action::Restart
+                | Action::Mark
+        )
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::SelectPrevious => &["k", "up"],
+            Action::SelectNext => &["j", "down"],
+            Action::PageUp => &["pageup"],
+            Action::PageDown => &["pagedown"],
+            Action::HalfPageUp => &["ctrl-u"],
+            Action::HalfPageDown => &["ctrl-d"],
+            Action::SelectFirst => &["g", "home"],
+            Action::SelectLast => &["G", "end"],
+            Action::Quit => &["q"],
+            Action::Help => &["?"],
+            Action::Filter => &["/"],
+            Action::Command => &[":"],
+            Action::Sort => &["s"],
+            Action::ReverseSort => &["S"],
+            Action::FilterMode => &["f"],
+            Action::Connections => &["c"],
+            Action::Pause => &["p"],
+            Action::PublicOnly => &["P"],
+            Action::Mine => &["u"],
+            Action::Tree => &["t"],
+            Action::SlowerUpdates => &["+"],
+            Action::FasterUpdates => &["-"],
+            Action::Refresh => &["r"],
+            Action::Kill => &["x"],
+            Action::ForceKill => &["X"],
+            Action::KillGroup => &["a"],
+            Action::PickSignal => &["K"],
+            Action::Restart => &["R"],
+            Action::Detail => &["l", "enter"],
+            Action::Mark => &["space"],
+            Action::OpenFiles => &["d"],
+            Action::KillLog => &["L"],
+            Action::ServiceNames => &["n"],
+            Action::Compact => &["C"],
+            Action::YankPid => &["y"],
+            Action::YankPorts => &["Y"],
+            Action::YankKill => &["ctrl-y"],
+            Action::OpenBrowser => &["b"],
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::iter()
+            .find(|action| action.to_string() == s)
+            .ok_or_else(|| format!("unknown action `{s}`"))
+    }
+}
+
+/// Keys with a name, and how the help shows them.
+const NAMED_KEYS: [(&str, KeyCode, &str); 12] = [
+    ("up", KeyCode::Up, "↑"),
+    ("down", KeyCode::Down, "↓"),
+    ("left", KeyCode::Left, "←"),
+    ("right", KeyCode::Right, "→"),
+    ("pageup", KeyCode::PageUp, "pgup"),
+    ("pagedown", KeyCode::PageDown, "pgdn"),
+    ("home", KeyCode::Home, "home"),
+    ("end", KeyCode::End, "end"),
+    ("enter", KeyCode::Enter, "enter"),
+    ("tab", KeyCode::Tab, "tab"),
+    ("space", KeyCode::Char(' '), "space"),
+    ("esc", KeyCode::Esc, "esc"),
+];
+
+/// A single key press. Shift is part of the character, e.g. `G`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Key {
+    code: KeyCode,
+    control: bool,
+}
+
+impl Key {
+    fn parse(name: &str) -> Option<Self> {
+        if let Some(c) = name.strip_prefix("ctrl-") {
+            let c = c.chars().exactly_one().ok()?;
+            return Some(Key {
+                code: KeyCode::Char(c),
+                control: true,
+            });
+        }
+        let (_, code, _) = NAMED_KEYS
+            .iter()
+            .find(|(named, _, _)| named.eq_ignore_ascii_case(name))?;
+        Some(Key {
+            code: *code,
+            control: false,
+        })
+    }
+
+    /// Esc and ^C always work the same, so there is a way out.
+    fn is_reserved(self) -> bool {
+        self.code == KeyCode::Esc || (self.control && self.code == KeyCode::Char('c'))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key {
+            code: event.code,
+            control: event.modifiers.contains(KeyModifiers::CONTROL),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.control {
+            write!(f, "^")?;
+        }
+        match NAMED_KEYS.iter().find(|(_, code, _)| *code == self.code) {
+            Some((_, _, shown)) => write!(f, "{shown}"),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{c}"),
+                code => write!(f, "{code}"),
+            },
+        }
+    }
+}
+
+/// Parse a key sequence like `x`, `gg`, `ctrl-d` or `g home`.
+/// Words are key names, or else each character is a key.
+fn parse_sequence(text: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    for word in text.split_whitespace() {
+        match Key::parse(word) {
+            Some(key) => keys.push(key),
+            None if word.starts_with("ctrl-") => return Err(format!("invalid key `{word}`")),
+            None => keys.extend(word.chars().map(|c| Key {
+                code: KeyCode::Char(c),
+                control: false,
+            })),
+        }
+    }
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+/// What a key press, together with the ones before it, is bound to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Lookup {
+    Action(Action),
+    /// The keys start a longer sequence, wait for the next one.
+    Pending,
+    Unbound,
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::iter()
+            .flat_map(|action| {
+                action.default_keys().iter().map(move |keys| {
+                    let keys = parse_sequence(keys).expect("default keys are valid");
+                    (keys, action)
+                })
+            })
+            .collect();
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// Replace the keys of the action with these sequences.
+    pub fn bind(&mut self, action: Action, sequences: &[String]) -> Result<(), String> {
+        let sequences = sequences
+            .iter()
+            .map(|text| parse_sequence(text))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(key) = sequences.iter().flatten().find(|key| key.is_reserved()) {
+            return Err(format!("<{key}> can not be rebound"));
+        }
+        self.bindings.retain(|&(_, bound)| bound != action);
+        self.bindings
+            .extend(sequences.into_iter().map(|keys| (keys, action)));
+        Ok(())
+    }
+
+    /// Bind one more sequence to the action, taking it away from the action it had before.
+    pub fn rebind(&mut self, sequence: &str, action: Action) -> Result<(), String> {
+        let keys = parse_sequence(sequence)?;
+        if let Some(key) = keys.iter().find(|key| key.is_reserved()) {
+            return Err(format!("<{key}> can not be rebound"));
+        }
+        self.bindings.retain(|(bound, _)| *bound != keys);
+        self.bindings.push((keys, action));
+        Ok(())
+    }
+
+    /// Make sure every key sequence does one thing only.
+    /// A sequence that starts another one, like `d` and `dd`, is a conflict too.
+    pub fn validate(&self) -> Result<(), String> {
+        for ((a, first), (b, second)) in self.bindings.iter().tuple_combinations() {
+            if first != second && (a.starts_with(b) || b.starts_with(a)) {
+                return Err(format!(
+                    "`{}` for {first} conflicts with `{}` for {second}",
+                    a.iter().join(""),
+                    b.iter().join(""),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::Unbound;
+        for (bound, action) in &self.bindings {
+            if bound == keys {
+                return Lookup::Action(*action);
+            }
+            if bound.starts_with(keys) {
+                lookup = Lookup::Pending;
+            }
+        }
+        lookup
+    }
+
+    /// The keys bound to an action as shown in the help, like `["k", "↑"]`.
+    pub fn keys(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|&&(_, bound)| bound == action)
+            .map(|(keys, _)| keys.iter().join(""))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(text: &str) -> Vec<Key> {
+        parse_sequence(text).unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        let keymap = Keymap::default();
+        keymap.validate().unwrap();
+        assert_eq!(keymap.lookup(&keys("x")), Lookup::Action(Action::Kill));
+        assert_eq!(
+            keymap.lookup(&keys("ctrl-d")),
+            Lookup::Action(Action::HalfPageDown)
+        );
+        assert_eq!(
+            keymap.lookup(&keys("G")),
+            Lookup::Action(Action::SelectLast)
+        );
+        assert_eq!(keymap.lookup(&keys("z")), Lookup::Unbound);
+        assert_eq!(keymap.keys(Action::SelectNext), ["j", "↓"]);
+    }
+
+    #[test]
+    fn sequences() {
+        let mut keymap = Keymap::default();
+        keymap
+            .bind(Action::SelectFirst, &["gg".to_string()])
+            .unwrap();
+        keymap.bind(Action::Kill, &["dd".to_string()]).unwrap();
+        assert!(keymap.validate().is_err());
+        keymap.bind(Action::OpenFiles, &["o".to_string()]).unwrap();
+        keymap.validate().unwrap();
+
+        assert_eq!(keymap.lookup(&keys("g")), Lookup::Pending);
+        assert_eq!(
+            keymap.lookup(&keys("gg")),
+            Lookup::Action(Action::SelectFirst)
+        );
+        assert_eq!(keymap.lookup(&keys("d d")), Lookup::Action(Action::Kill));
+        assert_eq!(keymap.lookup(&keys("x")), Lookup::Unbound);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut keymap = Keymap::default();
+        keymap.bind(Action::Refresh, &["x".to_string()]).unwrap();
+        assert_eq!(
+            keymap.validate(),
+            Err("`x` for kill conflicts with `x` for refresh".to_string())
+        );
+    }
+
+    #[test]
+    fn rebind() {
+        let mut keymap = Keymap::default();
+        keymap.rebind("enter", Action::Kill).unwrap();
+        keymap.validate().unwrap();
+        assert_eq!(keymap.lookup(&keys("enter")), Lookup::Action(Action::Kill));
+        assert_eq!(keymap.keys(Action::Kill), ["x", "enter"]);
+        assert_eq!(keymap.keys(Action::Detail), ["l"]);
+        assert!(keymap.rebind("esc", Action::Kill).is_err());
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let mut keymap = Keymap::default();
+        assert!(keymap.bind(Action::Quit, &["esc".to_string()]).is_err());
+        assert!(keymap.bind(Action::Quit, &["ctrl-c".to_string()]).is_err());
+        assert!(keymap.bind(Action::Quit, &["ctrl-".to_string()]).is_err());
+        assert!(keymap.bind(Action::Quit, &[" ".to_string()]).is_err());
+    }
+}
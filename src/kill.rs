@@ -0,0 +1,250 @@
+use std::process::Command;
+#[cfg(windows)]
+use std::process::Output;
+use std::str::FromStr;
+use std::{fmt, io};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Signals that can be sent to a process.
+/// The first variant is the default used by the plain kill action.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum Signal {
+    #[default]
+    Term,
+    Kill,
+    Hup,
+    Int,
+    Quit,
+    Usr1,
+    Usr2,
+}
+
+#[cfg(unix)]
+impl From<Signal> for nix::sys::signal::Signal {
+    fn from(signal: Signal) -> Self {
+        use nix::sys::signal::Signal as Nix;
+        match signal {
+            Signal::Term => Nix::SIGTERM,
+            Signal::Kill => Nix::SIGKILL,
+            Signal::Hup => Nix::SIGHUP,
+            Signal::Int => Nix::SIGINT,
+            Signal::Quit => Nix::SIGQUIT,
+            Signal::Usr1 => Nix::SIGUSR1,
+            Signal::Usr2 => Nix::SIGUSR2,
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    /// Parse names like `TERM`, `SIGKILL` or `hup`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        Signal::iter()
+            .find(|signal| signal.to_string() == name)
+            .ok_or_else(|| format!("unknown signal `{s}`"))
+    }
+}
+
+/// What to send a signal to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Target {
+    Process(usize),
+    /// All processes in the process group with this ID.
+    /// This includes children that were started by the group leader.
+    Group(usize),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Process(pid) => write!(f, "{pid}"),
+            Target::Group(pgid) => write!(f, "group {pgid}"),
+        }
+    }
+}
+
+/// Why a signal could not be sent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KillError {
+    /// The process does not exist, or has exited already.
+    NotFound,
+    /// The process belongs to someone else.
+    PermissionDenied,
+    /// Anything else, like a missing `kill` command, with its message.
+    Other(String),
+}
+
+impl fmt::Display for KillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KillError::NotFound => write!(f, "no such process"),
+            KillError::PermissionDenied => write!(f, "permission denied"),
+            KillError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for KillError {}
+
+impl From<io::Error> for KillError {
+    fn from(e: io::Error) -> Self {
+        KillError::Other(e.to_string())
+    }
+}
+
+/// Send the signal with the `kill` system call.
+#[cfg(unix)]
+pub fn kill(target: Target, signal: Signal) -> Result<(), KillError> {
+    use nix::sys::signal;
+    use nix::unistd::Pid;
+
+    let (Target::Process(pid) | Target::Group(pid)) = target;
+    // 0 and negative PIDs mean whole groups to the system call, never send to those by accident
+    let pid = match i32::try_from(pid) {
+        Ok(pid) if pid > 0 => Pid::from_raw(pid),
+        _ => return Err(KillError::NotFound),
+    };
+    let result = match target {
+        Target::Process(_) => signal::kill(pid, signal::Signal::from(signal)),
+        Target::Group(_) => signal::killpg(pid, signal::Signal::from(signal)),
+    };
+    result.map_err(from_errno)
+}
+
+#[cfg(unix)]
+fn from_errno(errno: nix::errno::Errno) -> KillError {
+    use nix::errno::Errno;
+    match errno {
+        Errno::ESRCH => KillError::NotFound,
+        Errno::EPERM => KillError::PermissionDenied,
+        errno => KillError::Other(errno.desc().to_string()),
+    }
+}
+
+/// Send the signal with `sudo kill`. sudo may ask for a password, so the terminal
+/// has to be usable for it.
+#[cfg(unix)]
+pub fn sudo_kill(target: Target, signal: Signal) -> io::Result<()> {
+    let status = Command::new("sudo")
+        .arg("kill")
+        .args(kill_args(target, signal))
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("sudo kill failed with {status}")))
+    }
+}
+
+/// The shell command that sends the signal, like `kill -TERM 1234`, to run it elsewhere.
+#[cfg(unix)]
+pub fn shell_command(target: Target, signal: Signal) -> String {
+    format!("kill {}", kill_args(target, signal).join(" "))
+}
+
+#[cfg(unix)]
+fn kill_args(target: Target, signal: Signal) -> Vec<String> {
+    let signal = format!("-{signal}");
+    match target {
+        Target::Process(pid) => vec![signal, pid.to_string()],
+        // A negative PID addresses the process group
+        Target::Group(pgid) => vec![signal, "--".to_string(), format!("-{pgid}")],
+    }
+}
+
+/// Windows has no signals, so anything but SIGKILL asks the process to close.
+/// There are no process groups either, a group kills the process tree instead.
+#[cfg(windows)]
+pub fn kill(target: Target, signal: Signal) -> Result<(), KillError> {
+    let output = Command::new("taskkill")
+        .args(taskkill_args(target, signal))
+        .output()?;
+    check(output, "Access is denied", "not found")
+}
+
+/// The command that sends the signal, like `taskkill /PID 1234`, to run it elsewhere.
+#[cfg(windows)]
+pub fn shell_command(target: Target, signal: Signal) -> String {
+    format!("taskkill {}", taskkill_args(target, signal).join(" "))
+}
+
+#[cfg(windows)]
+fn taskkill_args(target: Target, signal: Signal) -> Vec<String> {
+    let mut args = match target {
+        Target::Process(pid) => vec!["/PID".to_string(), pid.to_string()],
+        Target::Group(pid) => vec!["/T".to_string(), "/PID".to_string(), pid.to_string()],
+    };
+    if signal == Signal::Kill {
+        args.push("/F".to_string());
+    }
+    args
+}
+
+/// Turn a failed kill command into an error, recognized by its message.
+#[cfg(windows)]
+fn check(output: Output, permission_denied: &str, not_found: &str) -> Result<(), KillError> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(classify(stderr.trim(), permission_denied, not_found))
+}
+
+#[cfg(windows)]
+fn classify(message: &str, permission_denied: &str, not_found: &str) -> KillError {
+    if message.contains(permission_denied) {
+        KillError::PermissionDenied
+    } else if message.contains(not_found) {
+        KillError::NotFound
+    } else if message.is_empty() {
+        KillError::Other("the kill command failed".to_string())
+    } else {
+        KillError::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(windows)]
+    #[test]
+    fn errors() {
+        let classify = |message| classify(message, "Access is denied", "not found");
+        assert_eq!(
+            classify(
+                "ERROR: The process with PID 4 could not be terminated. Reason: Access is denied."
+            ),
+            KillError::PermissionDenied
+        );
+        assert_eq!(
+            classify("ERROR: The process \"99999\" not found."),
+            KillError::NotFound
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn errors() {
+        use nix::errno::Errno;
+        assert_eq!(from_errno(Errno::EPERM), KillError::PermissionDenied);
+        assert_eq!(from_errno(Errno::ESRCH), KillError::NotFound);
+        assert!(matches!(from_errno(Errno::EINVAL), KillError::Other(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kill_missing_process() {
+        // Above the largest PID Linux and macOS hand out
+        let target = Target::Process(99_999_999);
+        assert_eq!(kill(target, Signal::Term), Err(KillError::NotFound));
+        assert_eq!(
+            kill(Target::Group(0), Signal::Term),
+            Err(KillError::NotFound)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Find the processes that listen on ports.
+//!
+//! ```no_run
+//! use portwitch::{ScanOptions, default_source, processes};
+//!
+//! let scan = processes(default_source().as_ref(), ScanOptions::default()).unwrap();
+//! for process in scan.processes {
+//!     println!("{} {}", process.pid, process.command);
+//! }
+//! ```
+
+pub mod docker;
+pub mod interfaces;
+pub mod kill;
+#[cfg(unix)]
+pub mod lsof;
+// The parsers are tested on every platform
+#[cfg(any(windows, test))]
+pub mod netstat;
+pub mod procinfo;
+pub mod services;
+pub mod source;
+#[cfg(target_os = "linux")]
+pub mod ss;
+#[doc(hidden)]
+pub mod test_support;
+
+pub use crate::source::{
+    Family, PortSource, Process, Protocol, Scan, ScanOptions, Socket, default_source,
+};
+use std::{io, thread};
+
+/// Most lookups wait for the file system or a child process, not the CPU,
+/// but hundreds of threads would not help either.
+const MAX_LOOKUP_THREADS: usize = 8;
+
+/// Scan the source for processes that have at least one port.
+/// They are sorted by PID and their sockets by port, so the order does not depend on the source.
+pub fn processes(source: &dyn PortSource, options: ScanOptions) -> io::Result<Scan> {
+    let mut scan = source.scan(options)?;
+    if options.count_connections {
+        scan.connection_counts = source::connection_counts(&scan.processes);
+        // They were only read for counting
+        if !options.connections {
+            for process in &mut scan.processes {
+                process.ports.retain(|s| s.remote.is_none());
+            }
+        }
+    }
+    scan.processes.retain(|p| !p.ports.is_empty());
+    scan.processes.sort_by_key(|p| p.pid);
+    for process in &mut scan.processes {
+        process
+            .ports
+            .sort_by(|a, b| (a.port, a.key()).cmp(&(b.port, b.key())));
+    }
+    if options.uptime {
+        enrich(&mut scan.processes, |process| {
+            process.started = procinfo::start_time(process.pid);
+        });
+    }
+    Ok(scan)
+}
+
+/// Look up details of every process on a few threads.
+/// Each thread works on its own part of the list, so the order stays the same.
+fn enrich(processes: &mut [Process], lookup: impl Fn(&mut Process) + Sync) {
+    let threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_LOOKUP_THREADS);
+    let chunk_size = processes.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        for chunk in processes.chunks_mut(chunk_size) {
+            let lookup = &lookup;
+            scope.spawn(move || chunk.iter_mut().for_each(lookup));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{FixedSource, process, socket};
+
+    #[test]
+    fn processes_without_ports_are_hidden() {
+        let source = FixedSource(|| {
+            let mut client = process(1, "client", 80);
+            client.ports.clear();
+            vec![client, process(2, "server", 80)]
+        });
+
+        let scan = processes(&source, ScanOptions::default()).unwrap();
+        let pids: Vec<usize> = scan.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, [2]);
+    }
+
+    #[test]
+    fn order_does_not_depend_on_the_source() {
+        let first = FixedSource(|| {
+            let mut both = process(7, "server", 443);
+            both.ports.push(socket(80));
+            vec![process(30, "server", 8080), both, process(12, "server", 22)]
+        });
+        let second = FixedSource(|| {
+            let mut both = process(7, "server", 80);
+            both.ports.push(socket(443));
+            vec![process(12, "server", 22), process(30, "server", 8080), both]
+        });
+
+        let first = processes(&first, ScanOptions::default()).unwrap();
+        let second = processes(&second, ScanOptions::default()).unwrap();
+        assert_eq!(first.processes, second.processes);
+        let pids: Vec<usize> = first.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, [7, 12, 30]);
+        let ports: Vec<u16> = first.processes[0].ports.iter().map(|s| s.port).collect();
+        assert_eq!(ports, [80, 443]);
+    }
+
+    #[test]
+    fn connections_are_counted() {
+        let source = FixedSource(|| {
+            let mut server = process(1, "server", 80);
+            for (port, remote) in [(80, "10.0.0.1:50000"), (80, "10.0.0.2:50001")] {
+                let mut connection = socket(port);
+                connection.remote = Some(remote.to_string());
+                server.ports.push(connection);
+            }
+            // An outgoing connection, from a port nothing listens on
+            let mut client = process(2, "server", 51000);
+            client.ports[0].remote = Some("10.0.0.3:443".to_string());
+            vec![server, client, process(3, "server", 22)]
+        });
+        let options = ScanOptions {
+            count_connections: true,
+            ..ScanOptions::default()
+        };
+
+        let scan = processes(&source, options).unwrap();
+        let counts = &scan.connection_counts;
+        assert_eq!(counts[&(Protocol::Tcp, 80)], 2);
+        assert_eq!(counts[&(Protocol::Tcp, 22)], 0);
+        assert_eq!(counts.get(&(Protocol::Tcp, 51000)), None);
+        // Only counted, not shown
+        let pids: Vec<usize> = scan.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, [1, 3]);
+        assert_eq!(scan.processes[0].ports.len(), 1);
+
+        let options = ScanOptions {
+            connections: true,
+            ..options
+        };
+        let scan = processes(&source, options).unwrap();
+        assert_eq!(scan.processes[0].ports.len(), 3);
+        assert_eq!(scan.connection_counts[&(Protocol::Tcp, 80)], 2);
+    }
+
+    #[test]
+    fn enrich_keeps_the_order() {
+        let mut processes: Vec<Process> = (0..100).map(|pid| process(pid, "", 80)).collect();
+        enrich(&mut processes, |p| p.command = p.pid.to_string());
+        let commands: Vec<String> = (0..100).map(|pid| pid.to_string()).collect();
+        let found: Vec<String> = processes.into_iter().map(|p| p.command).collect();
+        assert_eq!(found, commands);
+    }
+}
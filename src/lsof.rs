@@ -1,76 +1,242 @@
+use crate::source::{Family, PortSource, Process, Protocol, Scan, ScanOptions, Socket};
 use itertools::Itertools;
 use std::collections::BTreeMap;
-use std::process::Command;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::thread;
 use strum::{EnumIter, IntoEnumIterator};
 
-pub fn lsof() -> Vec<Process> {
-    let stdout = Command::new("lsof")
-        .args(["-nP", "-F", "pcTPn0R", "-i"])
-        .output()
-        .unwrap()
-        .stdout;
-    parse_lsof_output(&stdout)
+/// Finds listening processes with `lsof`.
+#[derive(Debug)]
+pub struct Lsof;
+
+impl PortSource for Lsof {
+    fn scan(&self, options: ScanOptions) -> io::Result<Scan> {
+        // lsof always reports the fd, and the inode comes from files it looks at anyway,
+        // so asking for both costs nothing.
+        let mut child = Command::new("lsof")
+            .args(["-nP", "-F", "pgcufitTPn0R", "-i"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    io::Error::new(e.kind(), "lsof not found — please install it")
+                }
+                _ => io::Error::new(e.kind(), format!("failed to run lsof: {e}")),
+            })?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        // Read in the background, so lsof does not block on a full pipe while stdout is read
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        let stderr = thread::spawn(move || {
+            let mut text = String::new();
+            stderr.read_to_string(&mut text).map(|_| text)
+        });
+        let parsed = parse_lsof_output(BufReader::new(stdout), options);
+        let status = child.wait()?;
+        let stderr = stderr.join().ok().and_then(Result::ok).unwrap_or_default();
+        let parsed = parsed?;
+
+        let mut scan = Scan::from(parsed.processes);
+        scan.warnings
+            .extend(incomplete_warning(status.code(), &stderr));
+        if parsed.skipped > 0 {
+            let warning = format!("skipped {} malformed lsof records", parsed.skipped);
+            scan.warnings.push(warning);
+        }
+        Ok(scan)
+    }
+}
+
+/// Whether lsof said that its output may be incomplete, like when it could not look
+/// at the files of other users. It also exits with 1 when it finds nothing, which is fine.
+fn incomplete_warning(code: Option<i32>, stderr: &str) -> Option<String> {
+    if !stderr.trim().is_empty() {
+        return Some("lsof reported warnings — results may be incomplete".to_string());
+    }
+    match code {
+        Some(0 | 1) => None,
+        Some(code) => Some(format!(
+            "lsof exited with status {code} — results may be incomplete"
+        )),
+        None => Some("lsof was killed — results may be incomplete".to_string()),
+    }
 }
 
-fn parse_lsof_output(out: &[u8]) -> Vec<Process> {
-    let mut processes: Vec<Process> = Vec::new();
+/// `lsof -p <pid>`, which lists all files the process has open, in a human-readable table.
+pub fn open_files(pid: usize) -> Command {
+    let mut command = Command::new("lsof");
+    command.args(["-p", &pid.to_string()]);
+    command
+}
+
+/// The fields of one line, which describes a process or one of its files.
+type Fields = BTreeMap<FieldType, String>;
 
-    let all_attribute_sets = out.split(|&x| x == b'\n').map(parse_lsof_line);
-    let mut process_attributes = Vec::new();
-    for attribute_set in all_attribute_sets {
+#[derive(Debug)]
+struct LsofOutput {
+    processes: Vec<Process>,
+    /// Number of records that could not be parsed.
+    skipped: usize,
+}
+
+impl LsofOutput {
+    /// Add the process from its line and the lines of its files.
+    fn push(&mut self, sets: &[Fields], options: ScanOptions) {
+        if sets.is_empty() {
+            return;
+        }
+        match process_set(sets, options) {
+            Some(process) => self.processes.push(process),
+            None => self.skipped += 1,
+        }
+    }
+}
+
+/// Parse lsof output while it is read, skipping records that are malformed instead of failing.
+/// Every field ends with a NUL and a line ends with a newline after it, so a newline
+/// only starts a new line if it follows a NUL. Commands may contain newlines themselves.
+fn parse_lsof_output(mut out: impl BufRead, options: ScanOptions) -> io::Result<LsofOutput> {
+    let mut output = LsofOutput {
+        processes: Vec::new(),
+        skipped: 0,
+    };
+    // The lines of the current process
+    let mut process_attributes: Vec<Fields> = Vec::new();
+    let mut attribute_set = Fields::new();
+    let mut end_line = |set: Fields, process_attributes: &mut Vec<Fields>| {
+        if set.is_empty() {
+            return;
+        }
         // New process! Handle the previous one and clear
-        if attribute_set.contains_key(&FieldType::Pid) {
-            processes.extend(process_set(&process_attributes));
+        if set.contains_key(&FieldType::Pid) {
+            output.push(process_attributes, options);
             process_attributes.clear();
         }
-        process_attributes.push(attribute_set);
+        process_attributes.push(set);
+    };
+
+    let mut part = Vec::new();
+    loop {
+        part.clear();
+        if out.read_until(b'\0', &mut part)? == 0 {
+            break;
+        }
+        // A field without its NUL was cut off, and so was everything after it
+        let Some(field) = part.strip_suffix(b"\0") else {
+            break;
+        };
+        let field = match field.strip_prefix(b"\n") {
+            Some(field) => {
+                end_line(std::mem::take(&mut attribute_set), &mut process_attributes);
+                field
+            }
+            None => field,
+        };
+        if let Some((field, text)) = parse_lsof_part(field) {
+            attribute_set.insert(field, text.to_string());
+        }
     }
     // Process remaining attributes
-    processes.extend(process_set(&process_attributes));
+    end_line(attribute_set, &mut process_attributes);
+    output.push(&process_attributes, options);
 
-    processes
+    Ok(output)
 }
 
-fn process_set(x: &[BTreeMap<FieldType, &str>]) -> Option<Process> {
+/// Parse the attributes of one process and its files.
+/// Returns `None` if the process itself is malformed.
+fn process_set(x: &[Fields], options: ScanOptions) -> Option<Process> {
     let mut attributes = x.iter();
 
     // Process is always the first
     let process = attributes.next()?;
     let pid = process.get(&FieldType::Pid)?.parse().ok()?;
+    let pgid = process.get(&FieldType::Pgid).and_then(|g| g.parse().ok());
+    let ppid = process.get(&FieldType::Ppid).and_then(|r| r.parse().ok());
+    let uid = process.get(&FieldType::Uid).and_then(|u| u.parse().ok());
     let command = process.get(&FieldType::Command)?;
 
     let ports = attributes
-        .flat_map(|set| {
-            let network = set.get(&FieldType::Network)?;
-            let tcp = *set.get(&FieldType::TcpState)?;
-            if tcp == "LISTEN" {
-                Some(network.to_string())
-            } else {
-                None
-            }
-        })
+        .flat_map(|set| parse_socket(set, options))
         .unique()
         .collect();
 
     Some(Process {
         pid,
+        pgid,
+        ppid,
+        uid,
+        started: None,
         command: command.to_string(),
         ports,
     })
 }
 
-#[derive(Debug)]
-pub struct Process {
-    pub pid: usize,
-    pub command: String,
-    pub ports: Vec<String>,
+/// Parse a single file set into a socket, if it is one we are interested in.
+/// TCP sockets are only kept in LISTEN state, so we don't show outbound connections.
+/// Some lsof versions spell the states differently, like `LISTENING` or `Listen`.
+/// Some kernels don't tell the state, then a TCP socket without a peer is taken
+/// as listening.
+/// UDP has no state, so every bound socket that is not connected to a peer is kept.
+/// Connected sockets are kept too if the options ask for connections or their count.
+/// Without an address or a protocol there's no port to show, those sets are dropped.
+fn parse_socket(set: &Fields, options: ScanOptions) -> Option<Socket> {
+    let network = set.get(&FieldType::Network)?.as_str();
+    let (local, remote) = match network.split_once("->") {
+        Some((local, remote)) => (local, Some(remote)),
+        None => (network, None),
+    };
+    let protocol = match set.get(&FieldType::Protocol)?.as_str() {
+        "TCP" => Protocol::Tcp,
+        "UDP" => Protocol::Udp,
+        _ => return None,
+    };
+    if !options.wants(protocol) {
+        return None;
+    }
+
+    let keep = match protocol {
+        Protocol::Tcp => match set.get(&FieldType::TcpState) {
+            Some(state) => match state.to_uppercase().as_str() {
+                "LISTEN" | "LISTENING" => true,
+                "ESTABLISHED" | "ESTAB" => options.reads_connections(),
+                _ => false,
+            },
+            None => remote.is_none() || options.reads_connections(),
+        },
+        Protocol::Udp => remote.is_none() || options.reads_connections(),
+    };
+
+    if !keep {
+        return None;
+    }
+
+    let mut socket = Socket::parse_local(protocol, local)?;
+    // `*:80` does not tell the family, the type field does
+    match set.get(&FieldType::Type).map(String::as_str) {
+        Some("IPv4") => socket.family = Family::V4,
+        Some("IPv6") => socket.family = Family::V6,
+        _ => {}
+    }
+    socket.remote = remote.map(str::to_string);
+    socket.fd = set.get(&FieldType::Fd).cloned();
+    socket.inode = set.get(&FieldType::Inode).cloned();
+    Some(socket)
 }
 
 #[derive(Copy, Clone, EnumIter, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 enum FieldType {
     Pid,
+    Pgid,
+    Ppid,
     Command,
+    Uid,
+    Fd,
+    Inode,
+    Type,
     Network,
+    Protocol,
     TcpState,
 }
 
@@ -78,26 +244,460 @@ impl FieldType {
     fn prefix(self) -> &'static str {
         match self {
             FieldType::Pid => "p",
+            FieldType::Pgid => "g",
+            FieldType::Ppid => "R",
             FieldType::Command => "c",
+            FieldType::Uid => "u",
+            FieldType::Fd => "f",
+            FieldType::Inode => "i",
+            FieldType::Type => "t",
             FieldType::Network => "n",
-            FieldType::TcpState => "TST=",
+            FieldType::Protocol => "P",
+            // The state is one of the `T` subfields, see tcp_state
+            FieldType::TcpState => "T",
         }
     }
 }
 
-fn parse_lsof_line(line: &[u8]) -> BTreeMap<FieldType, &str> {
-    line.split(|&x| x == b'\0')
-        .filter_map(parse_lsof_part)
-        .collect()
-}
-
 fn parse_lsof_part(part: &[u8]) -> Option<(FieldType, &str)> {
     for field in FieldType::iter() {
         let prefix = field.prefix().as_bytes();
         if let Some(part) = part.strip_prefix(prefix) {
             let text = str::from_utf8(part).ok()?;
+            if field == FieldType::TcpState {
+                return Some((field, tcp_state(text)?));
+            }
             return Some((field, text));
         }
     }
     None
 }
+
+/// `T` comes as one field per subfield, like `TST=LISTEN`, `TQR=0` and `TQS=0`,
+/// in any order and not all of them on every system. Only the state is used.
+fn tcp_state(info: &str) -> Option<&str> {
+    let (name, value) = info.split_once('=')?;
+    (name == "ST").then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX: &[u8] = include_bytes!("../tests/fixtures/lsof-linux.txt");
+    const MACOS: &[u8] = include_bytes!("../tests/fixtures/lsof-macos.txt");
+    const NEWLINES: &[u8] = include_bytes!("../tests/fixtures/lsof-newlines.txt");
+    const STATES_LINUX: &[u8] = include_bytes!("../tests/fixtures/lsof-states-linux.txt");
+    const STATES_MACOS: &[u8] = include_bytes!("../tests/fixtures/lsof-states-macos.txt");
+    const NAMED_PORTS: &[u8] = include_bytes!("../tests/fixtures/lsof-named-ports.txt");
+    const INCOMPLETE: &[u8] = include_bytes!("../tests/fixtures/lsof-incomplete.txt");
+    const TCP_INFO: &[u8] = include_bytes!("../tests/fixtures/lsof-tcp-info.txt");
+
+    fn parse(out: &[u8]) -> LsofOutput {
+        parse_lsof_output(out, ScanOptions::default()).unwrap()
+    }
+
+    fn socket(protocol: Protocol, family: Family, address: &str, port: u16) -> Socket {
+        Socket {
+            protocol,
+            family,
+            address: address.to_string(),
+            port,
+            remote: None,
+            fd: None,
+            inode: None,
+        }
+    }
+
+    fn process(pid: usize, pgid: usize, command: &str, ports: Vec<Socket>) -> Process {
+        Process {
+            pid,
+            pgid: Some(pgid),
+            ppid: Some(1),
+            uid: None,
+            started: None,
+            command: command.to_string(),
+            ports,
+        }
+    }
+
+    #[test]
+    fn linux_fixture() {
+        let output = parse(LINUX);
+        assert_eq!(output.skipped, 0);
+        assert_eq!(
+            output.processes,
+            vec![
+                process(
+                    612,
+                    612,
+                    "systemd-resolve",
+                    vec![
+                        socket(Protocol::Udp, Family::V4, "127.0.0.53", 53),
+                        socket(Protocol::Tcp, Family::V4, "127.0.0.53", 53),
+                    ]
+                ),
+                process(
+                    1021,
+                    1021,
+                    "sshd",
+                    vec![
+                        socket(Protocol::Tcp, Family::V4, "*", 22),
+                        socket(Protocol::Tcp, Family::V6, "*", 22),
+                    ]
+                ),
+                Process {
+                    ppid: Some(4800),
+                    ..process(
+                        4821,
+                        4800,
+                        "node",
+                        vec![
+                            socket(Protocol::Tcp, Family::V4, "127.0.0.1", 3000),
+                            socket(Protocol::Tcp, Family::V6, "::1", 3001),
+                        ]
+                    )
+                },
+                // Only outgoing connections
+                process(5002, 5002, "firefox", vec![]),
+                process(
+                    6100,
+                    6100,
+                    "nginx",
+                    vec![
+                        socket(Protocol::Tcp, Family::V4, "*", 80),
+                        socket(Protocol::Tcp, Family::V6, "*", 80),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ipv6_wildcard() {
+        let output = parse(b"p1\0cnginx\0\nPTCP\0n[::]:80\0TST=LISTEN\0\n");
+        let bound = &output.processes[0].ports[0];
+        assert_eq!(bound, &socket(Protocol::Tcp, Family::V6, "*", 80));
+        assert_eq!(bound.to_string(), "80/tcp6");
+        assert_eq!(bound.network(), "*:80");
+    }
+
+    #[test]
+    fn family_from_address() {
+        // Without a type field the family is guessed from the address
+        let output = parse(b"p1\0cnode\0\nPTCP\0n[::1]:8080\0TST=LISTEN\0\n");
+        let socket = &output.processes[0].ports[0];
+        assert_eq!(socket.family, Family::V6);
+        assert_eq!(socket.network(), "[::1]:8080");
+    }
+
+    #[test]
+    fn macos_fixture() {
+        let output = parse(MACOS);
+        assert_eq!(output.skipped, 0);
+        assert_eq!(
+            output.processes,
+            vec![
+                process(
+                    386,
+                    386,
+                    "rapportd",
+                    vec![socket(Protocol::Tcp, Family::V4, "*", 49152)]
+                ),
+                process(
+                    501,
+                    501,
+                    "mDNSResponder",
+                    vec![socket(Protocol::Udp, Family::V4, "*", 5353)]
+                ),
+                Process {
+                    ppid: Some(700),
+                    ..process(
+                        777,
+                        777,
+                        "postgres",
+                        vec![
+                            socket(Protocol::Tcp, Family::V6, "::1", 5432),
+                            socket(Protocol::Tcp, Family::V4, "127.0.0.1", 5432),
+                            socket(Protocol::Tcp, Family::V6, "fe80:1::1", 5432),
+                        ]
+                    )
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp_info_in_any_order() {
+        let output = parse(TCP_INFO);
+        assert_eq!(output.skipped, 0);
+        let ports = output.processes[0]
+            .ports
+            .iter()
+            .map(|s| s.port)
+            .collect_vec();
+        // 7103 has no state and no peer, so it counts as listening too
+        assert_eq!(ports, [7100, 7101, 7102, 7103]);
+    }
+
+    #[test]
+    fn incomplete_sets() {
+        let ports = |options| {
+            let output = parse_lsof_output(INCOMPLETE, options).unwrap();
+            assert_eq!(output.skipped, 0);
+            output.processes[0]
+                .ports
+                .iter()
+                .map(|s| (s.protocol, s.port, s.remote.is_some()))
+                .collect_vec()
+        };
+        // No address, no state with or without a peer, no protocol, a closed socket
+        assert_eq!(
+            ports(ScanOptions::default()),
+            [(Protocol::Tcp, 7000, false), (Protocol::Udp, 7002, false)]
+        );
+        let options = ScanOptions {
+            connections: true,
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            ports(options),
+            [
+                (Protocol::Tcp, 7000, false),
+                (Protocol::Tcp, 7001, true),
+                (Protocol::Udp, 7002, false),
+                (Protocol::Udp, 7003, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn connections_fixture() {
+        let options = ScanOptions {
+            connections: true,
+            ..ScanOptions::default()
+        };
+        let output = parse_lsof_output(LINUX, options).unwrap();
+        let node = &output.processes[2];
+        assert_eq!(node.ports.len(), 3);
+        assert_eq!(node.ports[1].port, 3000);
+        assert_eq!(node.ports[1].remote.as_deref(), Some("127.0.0.1:51234"));
+
+        let firefox = &output.processes[3];
+        assert_eq!(
+            firefox
+                .ports
+                .iter()
+                .map(|s| s.remote.as_deref())
+                .collect_vec(),
+            vec![Some("93.184.216.34:443"), Some("192.168.1.1:53")]
+        );
+    }
+
+    #[test]
+    fn commands_with_newlines() {
+        let output = parse(NEWLINES);
+        assert_eq!(output.skipped, 0);
+        assert_eq!(
+            output.processes,
+            vec![
+                process(
+                    7001,
+                    7001,
+                    "my\nserver",
+                    vec![socket(Protocol::Tcp, Family::V4, "*", 8080)]
+                ),
+                process(
+                    7002,
+                    7002,
+                    "c:foo bar",
+                    vec![socket(Protocol::Tcp, Family::V4, "127.0.0.1", 9090)]
+                ),
+                process(
+                    7003,
+                    7003,
+                    "\npn\n",
+                    vec![socket(Protocol::Udp, Family::V6, "::1", 5000)]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_ports() {
+        let output = parse(NAMED_PORTS);
+        assert_eq!(output.skipped, 0);
+        let ports = output.processes[0]
+            .ports
+            .iter()
+            .map(|s| (s.protocol, s.address.as_str(), s.port))
+            .collect_vec();
+        // Names that are not known are dropped
+        assert_eq!(
+            ports,
+            [
+                (Protocol::Tcp, "*", 80),
+                (Protocol::Tcp, "::1", 443),
+                (Protocol::Udp, "*", 53),
+            ]
+        );
+    }
+
+    #[test]
+    fn tcp_states_linux() {
+        let output = parse(STATES_LINUX);
+        assert_eq!(output.skipped, 0);
+        assert_eq!(
+            output.processes,
+            vec![process(
+                812,
+                812,
+                "redis-ser",
+                vec![
+                    socket(Protocol::Tcp, Family::V4, "127.0.0.1", 6379),
+                    socket(Protocol::Tcp, Family::V6, "::1", 6379),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn parent_pid() {
+        let out = b"p42\0g42\0R7\0cnode\0\nf23\0tIPv4\0PTCP\0n*:3000\0TST=LISTEN\0\n";
+        assert_eq!(parse(out).processes[0].ppid, Some(7));
+        // Without the field the parent is not known
+        let out = b"p42\0g42\0cnode\0\nf23\0tIPv4\0PTCP\0n*:3000\0TST=LISTEN\0\n";
+        assert_eq!(parse(out).processes[0].ppid, None);
+    }
+
+    #[test]
+    fn file_descriptors() {
+        let out = b"p42\0g42\0cnode\0u501\0\nf23\0i81234\0tIPv4\0PTCP\0n*:3000\0TST=LISTEN\0\n";
+        let output = parse(out);
+        assert_eq!(output.processes[0].uid, Some(501));
+        let socket = &output.processes[0].ports[0];
+        assert_eq!(socket.fd.as_deref(), Some("23"));
+        assert_eq!(socket.inode.as_deref(), Some("81234"));
+
+        // The same socket on a second descriptor is listed once, with the first one
+        let output = parse(MACOS);
+        let sockets = &output.processes[0].ports;
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets[0].fd.as_deref(), Some("8"));
+        assert_eq!(sockets[0].inode, None);
+    }
+
+    #[test]
+    fn tcp_states_macos() {
+        let output = parse(STATES_MACOS);
+        assert_eq!(output.skipped, 0);
+        assert_eq!(
+            output.processes,
+            vec![
+                process(
+                    410,
+                    410,
+                    "ControlCe",
+                    vec![
+                        socket(Protocol::Tcp, Family::V4, "*", 7000),
+                        socket(Protocol::Tcp, Family::V4, "*", 5000),
+                    ]
+                ),
+                process(
+                    611,
+                    611,
+                    "node",
+                    vec![socket(Protocol::Tcp, Family::V4, "127.0.0.1", 3000)]
+                ),
+            ]
+        );
+
+        let options = ScanOptions {
+            connections: true,
+            ..ScanOptions::default()
+        };
+        let output = parse_lsof_output(STATES_MACOS, options).unwrap();
+        let node = &output.processes[1];
+        assert_eq!(node.ports.len(), 2);
+        assert_eq!(node.ports[0].remote.as_deref(), Some("127.0.0.1:50001"));
+    }
+
+    #[test]
+    fn udp_only() {
+        let options = ScanOptions {
+            protocol: Some(Protocol::Udp),
+            ..ScanOptions::default()
+        };
+        let output = parse_lsof_output(LINUX, options).unwrap();
+        let ports = output.processes.iter().flat_map(|p| &p.ports).collect_vec();
+        assert_eq!(
+            ports,
+            vec![&socket(Protocol::Udp, Family::V4, "127.0.0.53", 53)]
+        );
+    }
+
+    #[test]
+    fn empty_output() {
+        let output = parse(b"");
+        assert!(output.processes.is_empty());
+        assert_eq!(output.skipped, 0);
+    }
+
+    #[test]
+    fn process_without_command_is_skipped() {
+        let output = parse(b"p123\0g123\0R1\0\nPTCP\0n*:80\0TST=LISTEN\0\n");
+        assert!(output.processes.is_empty());
+        assert_eq!(output.skipped, 1);
+    }
+
+    #[test]
+    fn invalid_pid_is_skipped() {
+        let output = parse(
+            b"pabc\0cnginx\0\nPTCP\0n*:80\0TST=LISTEN\0\np2\0cnode\0\nPTCP\0n*:3000\0TST=LISTEN\0\n",
+        );
+        assert_eq!(output.skipped, 1);
+        assert_eq!(output.processes.len(), 1);
+        assert_eq!(output.processes[0].pid, 2);
+    }
+
+    #[test]
+    fn truncated_file_set() {
+        // Output cut off in the middle of a file set
+        let output = parse(b"p1\0cnode\0\nPTCP\0n*:30");
+        assert_eq!(output.skipped, 0);
+        assert_eq!(output.processes.len(), 1);
+        assert!(output.processes[0].ports.is_empty());
+    }
+
+    #[test]
+    fn garbage_does_not_panic() {
+        let inputs: [&[u8]; 5] = [
+            b"\xff\xfe\0\n\n\0\0\n",
+            b"PTCP\0n*:80\0TST=LISTEN\0\n",
+            b"p\0c\0\nP\0n\0TST=\0\n",
+            b"p1\0c\xff\xfe\0\nPTCP\0n*:99999\0TST=LISTEN\0\n",
+            b"p1\0cx\0\nPTCP\0n[::1\0TST=LISTEN\0\nPUDP\0n:\0\n",
+        ];
+        for input in inputs {
+            let output = parse(input);
+            assert!(output.processes.iter().all(|p| p.ports.is_empty()));
+        }
+    }
+
+    #[test]
+    fn incomplete_output() {
+        assert_eq!(incomplete_warning(Some(0), ""), None);
+        // Nothing found
+        assert_eq!(incomplete_warning(Some(1), "\n"), None);
+        let stderr = "lsof: WARNING: can't stat() fuse.portal file system /run/user/1000/doc\n      Output information may be incomplete.\n";
+        assert_eq!(
+            incomplete_warning(Some(1), stderr).unwrap(),
+            "lsof reported warnings — results may be incomplete"
+        );
+        assert_eq!(
+            incomplete_warning(Some(2), "").unwrap(),
+            "lsof exited with status 2 — results may be incomplete"
+        );
+        assert!(incomplete_warning(None, "").is_some());
+    }
+}
@@ -1,59 +1,616 @@
-mod lsof;
+mod browser;
+mod cli;
+mod clipboard;
+mod column;
+mod command;
+mod config;
+mod events;
+mod export;
+mod filter;
+mod history;
+mod keymap;
+mod reap;
+mod state;
+mod theme;
 
-use crate::lsof::Process;
+use crate::cli::{Args, Export, USAGE};
+use crate::column::Column;
+use crate::command::Command;
+use crate::config::Config;
+use crate::filter::{Filter, FilterMode, FuzzyMatch};
+use crate::history::History;
+use crate::keymap::{Action, Key, Keymap, Lookup};
+use crate::state::State;
+use crate::theme::{Theme, ThemeName};
 use itertools::Itertools;
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use portwitch::docker::{self, Container};
+use portwitch::interfaces;
+use portwitch::kill::{self, KillError, Signal, Target};
+#[cfg(unix)]
+use portwitch::lsof;
+use portwitch::procinfo::{self, Sample};
+use portwitch::services;
+use portwitch::source::shared_ports;
+use portwitch::{
+    Family, PortSource, Process, Protocol, Scan, ScanOptions, Socket, default_source, processes,
+};
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+#[cfg(unix)]
+use ratatui::crossterm::terminal::{EnterAlternateScreen, enable_raw_mode};
+use ratatui::layout::Flex;
 use ratatui::symbols::border;
-use ratatui::widgets::{Block, Clear, HighlightSpacing, List, Padding, Row, Table, TableState};
+use ratatui::widgets::{
+    Block, Clear, HighlightSpacing, List, ListState, Padding, Paragraph, Row, Scrollbar,
+    ScrollbarOrientation, ScrollbarState, Sparkline, Table, TableState,
+};
 use ratatui::{DefaultTerminal, prelude::*};
-use std::process::Command;
-use std::sync::mpsc::{Receiver, sync_channel};
-use std::time::Duration;
-use std::{env, io, thread};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::io::{BufRead, IsTerminal};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError, channel, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, io, panic, process, thread};
+use strum::{Display, EnumIter, IntoEnumIterator};
 
 const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+/// The update intervals that `--interval` and the config accept, in milliseconds.
+const INTERVAL_LIMITS: RangeInclusive<u64> = 10..=60_000;
+/// The steps of `+` and `-` for the update interval.
+const INTERVALS: [Duration; 7] = [
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+/// How long to wait for input before checking for the first scan again.
+const FIRST_SCAN_POLL: Duration = Duration::from_millis(50);
+/// How long a typed row number waits for more digits before jumping to the row.
+const COUNT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Shown when something would kill in read-only mode.
+const READ_ONLY: &str = "Killing is disabled in read-only mode.";
+/// How long to wait for a restarted process to exit before starting it again.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long new and gone processes are highlighted.
+const CHANGE_DURATION: Duration = Duration::from_millis(1500);
+/// How many scans the connection counts in the detail pane go back.
+const CONNECTION_HISTORY: usize = 30;
+/// How often the updater thread is started again after it crashed, before giving up.
+const UPDATER_RESTARTS: usize = 3;
+/// How long the result of a kill stays on the screen.
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() -> io::Result<()> {
-    let args = env::args().skip(1).join(" ");
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("portwitch: {e}");
+            process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{USAGE}");
+        return Ok(());
+    }
+    if args.version {
+        println!("portwitch {VERSION}");
+        return Ok(());
+    }
+    if args.watch_stdin && io::stdin().is_terminal() {
+        eprintln!(
+            "portwitch: --watch-stdin needs ports piped to stdin, like `echo 8080 | portwitch --watch-stdin`"
+        );
+        process::exit(2);
+    }
+    let bind = match (&args.address, &args.interface) {
+        (Some(address), _) => Some(Bind::address(address)),
+        (None, Some(interface)) => match interfaces::addresses(interface) {
+            Ok(addresses) if !addresses.is_empty() => Some(Bind {
+                name: interface.clone(),
+                addresses,
+            }),
+            Ok(_) => {
+                eprintln!("portwitch: {interface} has no addresses");
+                process::exit(2);
+            }
+            Err(e) => {
+                eprintln!("portwitch: could not find the addresses of {interface}: {e}");
+                process::exit(2);
+            }
+        },
+        (None, None) => None,
+    };
+
+    let scan = ScanOptions {
+        connections: args.connections,
+        protocol: args.protocol,
+        ..ScanOptions::default()
+    };
+
+    if let Some(format) = args.export {
+        let result = processes(default_source().as_ref(), scan)?;
+        for warning in &result.warnings {
+            eprintln!("portwitch: {warning}");
+        }
+        let procs = result.processes;
+        match format {
+            Export::Json => println!("{}", export::json(&procs)),
+            Export::Csv => print!("{}", export::csv(&procs)),
+        }
+        return Ok(());
+    }
+
+    let (config, warning) = Config::load();
+
+    if let Some(port) = args.reap {
+        if let Some(warning) = warning {
+            eprintln!("portwitch: {warning}");
+        }
+        if config.read_only {
+            eprintln!("portwitch: --reap kills, which read_only in the config does not allow");
+            process::exit(2);
+        }
+        let source = default_source();
+        let signal = config.kill_signal;
+        return reap::reap(
+            source.as_ref(),
+            port,
+            signal,
+            args.interval
+                .or(config.update_interval)
+                .unwrap_or(UPDATE_INTERVAL),
+            args.once,
+        );
+    }
+
+    if args.events {
+        if let Some(warning) = warning {
+            eprintln!("portwitch: {warning}");
+        }
+        let interval = args
+            .interval
+            .or(config.update_interval)
+            .unwrap_or(UPDATE_INTERVAL);
+        return events::watch(default_source().as_ref(), scan, interval);
+    }
+
+    // The TUI would write escape codes into a file or pipe
+    if !io::stdout().is_terminal() {
+        eprintln!(
+            "portwitch: stdout is not a terminal, use --json or --csv to print the list instead"
+        );
+        process::exit(2);
+    }
+    // Keys are read from the controlling terminal when stdin is piped
+    let stdin_piped = !io::stdin().is_terminal();
+    #[cfg(unix)]
+    if stdin_piped && std::fs::File::open("/dev/tty").is_err() {
+        eprintln!(
+            "portwitch: there is no terminal to read keys from, use --json or --csv to print the list instead"
+        );
+        process::exit(2);
+    }
+    // Without --watch-stdin, what is piped in is the filter, like `echo 8080 | portwitch`
+    let filter = match stdin_piped && !args.watch_stdin && args.filter.is_empty() {
+        true => read_stdin_filter(),
+        false => args.filter,
+    };
 
-    let receiver = spawn_process_updater();
+    let state = State::load();
+
+    let scan = Arc::new(Mutex::new(ScanOptions {
+        uptime: config.show_uptime,
+        count_connections: args.count_connections || config.count_connections,
+        ..scan
+    }));
+    let update_interval = args
+        .interval
+        .or(config.update_interval)
+        .or(state.update_interval)
+        .unwrap_or(UPDATE_INTERVAL);
+    // Shared with the updater, which starts it again after a crash
+    let source: Arc<dyn PortSource> = default_source().into();
+    let (receiver, intervals) =
+        spawn_process_updater(source.clone(), update_interval, scan.clone());
+    // See https://no-color.org
+    let no_color = args.no_color || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let mut theme = match no_color {
+        true => ThemeName::Plain,
+        false => args.theme.unwrap_or(config.theme),
+    }
+    .theme();
+    if let Some(symbol) = config.highlight_symbol {
+        theme.highlight_symbol = symbol;
+    }
+    if let Some(style) = config.highlight_style
+        && !no_color
+    {
+        theme.highlight = style;
+    }
 
     let mut app = App {
-        filter: args,
+        filter: if filter.is_empty() {
+            config.default_filter
+        } else {
+            filter
+        },
         receiver,
-        processes: processes(),
+        intervals,
+        updater_crashes: 0,
+        processes: Vec::new(),
+        warnings: Vec::new(),
+        error: None,
         exit: false,
         table: TableState::default(),
         state: AppState::default(),
+        status: warning,
+        sort: state.sort,
+        sort_reversed: state.sort_reversed,
+        filter_mode: state.filter_mode,
+        kill_signal: config.kill_signal,
+        confirm_kill: config.confirm_kill,
+        confirm_quit: config.confirm_quit,
+        update_interval,
+        scan,
+        source,
+        paused: false,
+        public_only: false,
+        mine: args.mine,
+        port_range: match (
+            args.min_port.or(config.min_port),
+            args.max_port.or(config.max_port),
+        ) {
+            (None, None) => None,
+            (min, max) => Some(min.unwrap_or(0)..=max.unwrap_or(u16::MAX)),
+        },
+        tree: args.pid,
+        parents: HashMap::new(),
+        bind,
+        watch_ports: args.watch_ports.unwrap_or(config.watch_ports),
+        stdin_ports: args.watch_stdin.then(spawn_stdin_reader),
+        table_layout: TableLayout::default(),
+        help_page: 1,
+        command_lines: HashMap::new(),
+        changes: HashMap::new(),
+        connection_counts: HashMap::new(),
+        port_connections: HashMap::new(),
+        theme,
+        marked: HashSet::new(),
+        usage: Vec::new(),
+        page_open_files: None,
+        shared_ports: HashSet::new(),
+        filter_history: History::load(),
+        filter_cache: RefCell::default(),
+        columns: config.columns,
+        scanning: true,
+        keymap: config.keymap,
+        pending_keys: Vec::new(),
+        count: None,
+        docker: args.docker || config.docker,
+        sudo: config.sudo,
+        sudo_retry: None,
+        restart: None,
+        last_message: None,
+        kill_log: Vec::new(),
+        show_kill_log: false,
+        service_names: config.service_names,
+        compact: args.compact,
+        read_only: args.read_only || config.read_only,
+        dirty: true,
+        clear: false,
+        containers: None,
+    };
+
+    set_panic_hook();
+    ratatui::run(|terminal| {
+        execute!(io::stdout(), EnableMouseCapture)?;
+        let result = app.run(terminal);
+        execute!(io::stdout(), DisableMouseCapture)?;
+        result
+    })?;
+
+    let state = State {
+        sort: app.sort,
+        sort_reversed: app.sort_reversed,
+        filter_mode: app.filter_mode,
+        update_interval: match args.interval {
+            // The config file wins, so a change in the UI is only for this run
+            _ if config.update_interval.is_some() => state.update_interval,
+            // A one-off --interval is not remembered, unless it was changed in the UI
+            Some(interval) if interval == app.update_interval => state.update_interval,
+            _ => (app.update_interval != UPDATE_INTERVAL).then_some(app.update_interval),
+        },
+    };
+    if let Err(e) = state.save() {
+        eprintln!("portwitch: could not save state: {e}");
+    }
+    Ok(())
+}
+
+/// Start a command in the background, so it does not draw into the TUI
+/// and keeps running when portwitch exits.
+fn spawn_detached(args: &[OsString], dir: Option<PathBuf>) -> io::Result<u32> {
+    let (program, args) = args.split_first().expect("arguments are never empty");
+    let mut command = process::Command::new(program);
+    command
+        .args(args)
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    // Its own process group, so ^C in our terminal does not reach it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn()?;
+    let pid = child.id();
+    // Reap it if it exits while we run, so it does not stay around as a zombie
+    thread::spawn(move || child.wait());
+    Ok(pid)
+}
+
+/// Leave the TUI to run something that uses the terminal, and restore it after.
+#[cfg(unix)]
+fn suspend<T>(terminal: &mut DefaultTerminal, run: impl FnOnce() -> T) -> io::Result<T> {
+    execute!(io::stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    let result = run();
+    resume(terminal)?;
+    Ok(result)
+}
+
+/// Set up the terminal again after it was restored.
+fn resume(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    // Everything has to be drawn again
+    terminal.clear()
+}
+
+/// Restore the terminal before a panic message is printed, so the shell stays usable.
+/// ratatui installs a similar hook, but it does not know about mouse capture.
+fn set_panic_hook() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The hook of ratatui, chained after this one, restores the terminal for a panic
+        // in any thread. If the updater crashed, the TUI sets the terminal up again.
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+        ratatui::restore();
+        hook(info);
+    }));
+}
+
+/// The row to select in a list of PIDs that changed. That is the previously selected
+/// PID if it is still there, else the row at the same position, or the nearest one.
+/// The first row is selected if nothing was selected before.
+fn restored_selection(previous: Option<(usize, usize)>, pids: &[usize]) -> Option<usize> {
+    let last = pids.len().checked_sub(1)?;
+    let i = match previous {
+        Some((pid, i)) => pids.iter().position(|&p| p == pid).unwrap_or(i.min(last)),
+        None => 0,
     };
+    Some(i)
+}
+
+/// Read stdin until it ends and make a filter that matches any of its words,
+/// so a list of ports or commands shows all of them.
+fn read_stdin_filter() -> String {
+    let text = io::read_to_string(io::stdin()).unwrap_or_default();
+    stdin_filter(&text)
+}
+
+fn stdin_filter(text: &str) -> String {
+    text.split_whitespace().join("|")
+}
+
+/// Spawn a thread that reads ports to watch from stdin until it ends.
+/// Lines that are not a port are sent as an error, to be reported.
+fn spawn_stdin_reader() -> Receiver<Result<u16, String>> {
+    let (sender, receiver) = sync_channel(64);
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Some(port) = parse_watched_port(&line) else {
+                continue;
+            };
+            if sender.send(port).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+/// Parse a line like `8080` or `:8080`. Empty lines are skipped.
+fn parse_watched_port(line: &str) -> Option<Result<u16, String>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let port = line.strip_prefix(':').unwrap_or(line);
+    Some(port.parse().map_err(|_| line.to_string()))
+}
+
+/// An interval like `500ms`, `1s` or `1.5s`.
+fn format_interval(interval: Duration) -> String {
+    let ms = interval.as_millis();
+    match ms {
+        0..1000 => format!("{ms}ms"),
+        _ if ms.is_multiple_of(1000) => format!("{}s", ms / 1000),
+        _ => format!("{:.1}s", interval.as_secs_f64()),
+    }
+}
 
-    ratatui::run(|terminal| app.run(terminal))
+/// The next step after the interval, `None` at the end. Intervals between steps
+/// from the config go to the step next to them.
+fn next_interval(interval: Duration, longer: bool) -> Option<Duration> {
+    match longer {
+        true => INTERVALS.into_iter().find(|&i| i > interval),
+        false => INTERVALS.into_iter().rev().find(|&i| i < interval),
+    }
 }
 
 /// Spawn a thread for updating the list of processes.
-/// Returns a receiver for receiving the updates.
-fn spawn_process_updater() -> Receiver<Vec<Process>> {
-    let (sender, receiver) = sync_channel(0);
+/// Returns a receiver for receiving the updates, and a sender for changing the interval.
+fn spawn_process_updater(
+    source: Arc<dyn PortSource>,
+    mut interval: Duration,
+    scan: Arc<Mutex<ScanOptions>>,
+) -> (Receiver<io::Result<Scan>>, Sender<Duration>) {
+    // One slot, so a scan can be handed over without waiting for the UI to pick it up.
+    let (sender, receiver) = sync_channel(1);
+    let (intervals, changes) = channel();
 
     thread::spawn(move || {
         loop {
-            let procs = processes();
+            let scanned = Instant::now();
+            let options = *scan.lock().unwrap();
+            let procs = processes(source.as_ref(), options);
             if sender.send(procs).is_err() {
                 break;
             }
+            // Scanning is expensive, don't do it more often than the UI updates.
+            // A changed interval applies to the wait that is already running.
+            loop {
+                let wait = (scanned + interval).saturating_duration_since(Instant::now());
+                match changes.recv_timeout(wait) {
+                    Ok(changed) => interval = changed,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
         }
     });
 
-    receiver
+    (receiver, intervals)
 }
 
 #[derive(Debug, Default)]
 enum AppState {
     #[default]
     ShowList,
-    ShowHelp,
+    /// The help, scrolled down by this many lines.
+    ShowHelp(usize),
     EditFilter(String),
+    /// A command typed after `:`.
+    Command(String),
+    PickSignal(ListState),
+    /// Show all sockets of the process with this PID.
+    Detail(usize),
+    /// Ask before sending the signal.
+    ConfirmKill(Vec<Target>, Signal),
+    /// Killing was not permitted, ask to try again with sudo.
+    ConfirmSudo(Vec<Target>, Signal),
+    /// Ask before restarting the process with this PID.
+    ConfirmRestart(usize),
+    ConfirmQuit,
+}
+
+/// A restarted process that was killed, and whose command starts again once it has exited.
+#[derive(Debug)]
+struct PendingRestart {
+    pid: usize,
+    name: String,
+    args: Vec<OsString>,
+    dir: Option<PathBuf>,
+    killed: Instant,
+}
+
+/// The addresses from `--address` or `--iface` that processes must be reachable on.
+#[derive(Debug)]
+struct Bind {
+    /// The address or interface, as given.
+    name: String,
+    /// Empty for all interfaces, like `*` or `0.0.0.0`.
+    addresses: Vec<String>,
+}
+
+impl Bind {
+    fn address(address: &str) -> Self {
+        let host = address.trim_start_matches('[').trim_end_matches(']');
+        let addresses = match host {
+            "*" | "0.0.0.0" | "::" => Vec::new(),
+            host => vec![host.to_string()],
+        };
+        Bind {
+            name: address.to_string(),
+            addresses,
+        }
+    }
+
+    /// Wildcard sockets are reachable on every address, and they are the only ones
+    /// reachable on all of them.
+    fn matches(&self, socket: &Socket) -> bool {
+        socket.address == "*" || self.addresses.contains(&socket.address)
+    }
+}
+
+/// How a process changed with the last scans.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Change {
+    Appeared,
+    /// The process is gone, but stays in the list until its highlight is over.
+    Gone,
+}
+
+/// Columns the process list can be sorted by.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "lowercase")]
+enum SortKey {
+    #[default]
+    Pid,
+    Command,
+    Port,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortKey::iter()
+            .find(|key| key.to_string() == s)
+            .ok_or_else(|| format!("unknown sort column `{s}`"))
+    }
+}
+
+impl SortKey {
+    /// The next key when cycling through the sort columns.
+    fn next(self) -> Self {
+        Self::iter()
+            .cycle()
+            .skip_while(|&k| k != self)
+            .nth(1)
+            .unwrap()
+    }
+
+    /// Compare two processes by this key, using the PID to break ties.
+    fn compare(self, a: &Process, b: &Process) -> Ordering {
+        let lowest_port = |p: &Process| p.ports.iter().map(|s| s.port).min();
+        let ordering = match self {
+            SortKey::Pid => Ordering::Equal,
+            SortKey::Command => a.command.to_lowercase().cmp(&b.command.to_lowercase()),
+            SortKey::Port => lowest_port(a).cmp(&lowest_port(b)),
+        };
+        ordering.then(a.pid.cmp(&b.pid))
+    }
 }
 
 #[derive(Debug)]
@@ -65,7 +622,136 @@ struct App {
     table: TableState,
     filter: String,
     state: AppState,
-    receiver: Receiver<Vec<Process>>,
+    receiver: Receiver<io::Result<Scan>>,
+    /// Changes the interval of the updater thread.
+    intervals: Sender<Duration>,
+    /// How often the updater thread crashed.
+    updater_crashes: usize,
+    /// Warnings of the last scan.
+    warnings: Vec<String>,
+    /// Set if the last scan failed. The list cannot be used then.
+    error: Option<String>,
+    /// A message for the user, shown in the bottom line until the next key press.
+    status: Option<String>,
+    sort: SortKey,
+    sort_reversed: bool,
+    filter_mode: FilterMode,
+    /// The signal used by the plain kill action.
+    kill_signal: Signal,
+    confirm_kill: bool,
+    /// Ask before quitting with <q> or <esc>.
+    confirm_quit: bool,
+    update_interval: Duration,
+    /// Shared with the updater thread, so changes apply to the next scan.
+    scan: Arc<Mutex<ScanOptions>>,
+    /// Used for scans on demand, the updater thread has its own.
+    source: Arc<dyn PortSource>,
+    /// Ignore updates, so the list stays as it is.
+    paused: bool,
+    /// Hide processes that only listen on loopback addresses.
+    public_only: bool,
+    /// Hide processes of other users.
+    mine: bool,
+    /// Hide processes without a port in this range, from `--min-port` and `--max-port`.
+    port_range: Option<RangeInclusive<u16>>,
+    /// Hide processes that are not reachable on these addresses.
+    bind: Option<Bind>,
+    /// Only show this process and its descendants, from `--pid` or the selected row.
+    tree: Option<usize>,
+    /// The parents of the processes and of their ancestors up to the root of the tree.
+    parents: HashMap<usize, Option<usize>>,
+    /// Processes listening on one of these ports are highlighted.
+    watch_ports: Vec<u16>,
+    /// More ports to watch from `--watch-stdin`, until stdin ends.
+    stdin_ports: Option<Receiver<Result<u16, String>>>,
+    /// Where the table was rendered last, for mapping mouse clicks.
+    table_layout: TableLayout,
+    /// How many lines of the help fit on the screen, for scrolling a page.
+    help_page: usize,
+    /// Full command lines by PID, looked up once when the details are opened.
+    command_lines: HashMap<usize, Option<String>>,
+    /// Processes that appeared or disappeared recently, by PID, and when.
+    changes: HashMap<usize, (Change, Instant)>,
+    /// The number of connections of each process in the last scans, while they are shown.
+    connection_counts: HashMap<usize, VecDeque<u64>>,
+    /// The established connections of each listening port, if they are counted.
+    port_connections: HashMap<(Protocol, u16), usize>,
+    theme: Theme,
+    /// PIDs of the processes marked for killing them together.
+    marked: HashSet<usize>,
+    /// The last two CPU and memory samples of the process in the detail pane.
+    usage: Vec<Sample>,
+    /// Show the open files of this PID in the pager, which needs the terminal.
+    page_open_files: Option<usize>,
+    /// Ports that more than one process listens on.
+    shared_ports: HashSet<(Protocol, u16)>,
+    filter_history: History,
+    filter_cache: RefCell<FilterCache>,
+    columns: Vec<Column>,
+    /// No scan has finished yet, the list is empty until then.
+    scanning: bool,
+    keymap: Keymap,
+    /// The start of a key sequence like `gg`.
+    pending_keys: Vec<Key>,
+    /// Digits typed so far, as a row to jump to or a count for `j` and `k`.
+    count: Option<(usize, Instant)>,
+    /// Look up the containers behind Docker proxies.
+    docker: bool,
+    /// Offer `sudo kill` when killing is not permitted.
+    sudo: bool,
+    /// Kills to retry with sudo once the TUI is suspended.
+    sudo_retry: Option<(Vec<Target>, Signal)>,
+    /// A restart waiting for the old process to exit.
+    restart: Option<PendingRestart>,
+    /// The result of the last kill, shown for a little while.
+    last_message: Option<(String, Instant)>,
+    /// What was killed in this session.
+    kill_log: Vec<(Instant, String)>,
+    show_kill_log: bool,
+    /// Show names like `https` next to well-known ports.
+    service_names: bool,
+    /// Only the rows of the list, without titles and header.
+    compact: bool,
+    /// Killing is disabled, from `--read-only` or the config.
+    read_only: bool,
+    /// Something changed since the last frame, so it has to be drawn again.
+    dirty: bool,
+    /// A thread panicked, and the panic hook restored the terminal for its message.
+    /// The terminal has to be set up and drawn again.
+    clear: bool,
+    /// The containers when the detail pane shows a Docker proxy.
+    containers: Option<Result<Vec<Container>, String>>,
+}
+
+/// The rows of the filtered list, as indices into the processes.
+/// Only computed again if the processes or anything in the key changed.
+#[derive(Debug, Default)]
+struct FilterCache {
+    key: Option<FilterKey>,
+    indices: Vec<usize>,
+    /// How deep each process is below the root of the tree, by PID.
+    depths: HashMap<usize, usize>,
+    /// The characters of the command that a fuzzy filter matched, by PID.
+    matched: HashMap<usize, Vec<usize>>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct FilterKey {
+    text: String,
+    mode: FilterMode,
+    sort: SortKey,
+    sort_reversed: bool,
+    public_only: bool,
+    mine: bool,
+    tree: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct TableLayout {
+    header: Rect,
+    rows: Rect,
+    /// The horizontal position of each column and the sort key it stands for.
+    columns: Vec<(Rect, Option<SortKey>)>,
 }
 
 impl App {
@@ -73,29 +759,365 @@ impl App {
     fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
         while !self.exit {
             self.refresh_processes();
-            terminal.draw(|frame| self.draw(frame))?;
+            self.update_usage();
+            self.expire_message();
+            self.expire_count();
+            self.expire_changes();
+            self.receive_stdin_ports();
+            self.continue_restart();
+            // Drawing a frame that did not change is wasted work, especially over SSH.
+            if std::mem::take(&mut self.clear) {
+                resume(terminal)?;
+            }
+            if self.dirty {
+                terminal.draw(|frame| self.draw(frame))?;
+                self.dirty = false;
+            }
             self.handle_events()?;
+            if let Some(pid) = self.page_open_files.take() {
+                self.show_open_files(terminal, pid)?;
+                self.dirty = true;
+            }
+            if let Some((targets, signal)) = self.sudo_retry.take() {
+                self.sudo_kill(terminal, &targets, signal)?;
+                self.dirty = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Leave the TUI to show `lsof -p` in `$PAGER`, and come back after.
+    #[cfg(unix)]
+    fn show_open_files(&mut self, terminal: &mut DefaultTerminal, pid: usize) -> io::Result<()> {
+        let result = suspend(terminal, || run_in_pager(lsof::open_files(pid)))?;
+        if let Err(e) = result {
+            self.status = Some(format!("Could not show open files: {e}."));
         }
         Ok(())
     }
 
+    #[cfg(windows)]
+    fn show_open_files(&mut self, _terminal: &mut DefaultTerminal, _pid: usize) -> io::Result<()> {
+        self.status = Some("Open files can only be shown with lsof.".to_string());
+        Ok(())
+    }
+
+    /// Leave the TUI so sudo can ask for a password, and come back after.
+    #[cfg(unix)]
+    fn sudo_kill(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        targets: &[Target],
+        signal: Signal,
+    ) -> io::Result<()> {
+        let result = suspend(terminal, || {
+            targets
+                .iter()
+                .try_for_each(|&target| kill::sudo_kill(target, signal))
+        })?;
+        let entry = match &result {
+            Ok(()) => {
+                let names = targets.iter().map(|&t| self.describe(t, true)).join(", ");
+                format!("sent SIG{signal} to {names} with sudo")
+            }
+            Err(e) => format!("sudo kill failed: {e}"),
+        };
+        self.kill_log.push((Instant::now(), entry));
+        let message = match (result, targets) {
+            (Ok(()), [target]) => format!("Sent SIG{signal} to {target} with sudo."),
+            (Ok(()), _) => format!("Sent SIG{signal} to {} processes with sudo.", targets.len()),
+            (Err(e), _) => format!("{e}."),
+        };
+        self.last_message = Some((message, Instant::now()));
+        self.refresh_processes();
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn sudo_kill(
+        &mut self,
+        _terminal: &mut DefaultTerminal,
+        _targets: &[Target],
+        _signal: Signal,
+    ) -> io::Result<()> {
+        self.status = Some("sudo is not available on Windows.".to_string());
+        Ok(())
+    }
+
     fn refresh_processes(&mut self) {
-        // To keep a stable selection, we will remember the PID of the selected process
-        // before updating and restore it after.
-        let selected_pid = self
-            .table
-            .selected()
-            .and_then(|i| self.filtered_list().nth(i))
-            .map(|p| p.pid);
+        // Pausing should not keep the list empty.
+        if self.paused && !self.scanning {
+            return;
+        }
+
+        // The list stays as it is when the updater keeps crashing, only `r` scans then.
+        if self.updates_stopped() {
+            return;
+        }
 
         // We expect a value to be in the channel, no waiting.
-        if let Ok(procs) = self.receiver.recv_timeout(Duration::ZERO) {
-            self.processes = procs;
+        match self.receiver.recv_timeout(Duration::ZERO) {
+            Ok(procs) => self.set_processes(procs),
+            Err(RecvTimeoutError::Timeout) => {}
+            // The updater panicked, and the panic hook left the TUI for its message.
+            Err(RecvTimeoutError::Disconnected) => {
+                self.updater_crashes += 1;
+                self.clear = true;
+                self.dirty = true;
+                if self.updates_stopped() {
+                    self.scanning = false;
+                    return;
+                }
+                (self.receiver, self.intervals) = spawn_process_updater(
+                    self.source.clone(),
+                    self.update_interval,
+                    self.scan.clone(),
+                );
+                let message = "The scan crashed and was restarted.".to_string();
+                self.last_message = Some((message, Instant::now()));
+            }
+        }
+    }
+
+    /// Go to the next longer or shorter step of the update interval.
+    fn change_interval(&mut self, longer: bool) {
+        let Some(interval) = next_interval(self.update_interval, longer) else {
+            return;
+        };
+        self.update_interval = interval;
+        // Fails if the updater crashed, a restarted one gets the new interval
+        let _ = self.intervals.send(interval);
+    }
+
+    fn updates_stopped(&self) -> bool {
+        self.updater_crashes > UPDATER_RESTARTS
+    }
+
+    /// Scan right now instead of waiting for the updater. Works while paused.
+    fn force_refresh(&mut self) {
+        // Drop a pending update, it is older than the scan we are about to do.
+        while self.receiver.try_recv().is_ok() {}
+        let procs = processes(self.source.as_ref(), self.scan_options());
+        self.set_processes(procs);
+    }
+
+    /// Sample the CPU and memory use of the process in the detail pane once per interval.
+    fn update_usage(&mut self) {
+        let AppState::Detail(pid) = self.state else {
+            return;
+        };
+        let due = self
+            .usage
+            .last()
+            .is_none_or(|sample| sample.taken().elapsed() >= self.update_interval);
+        if !due {
+            return;
+        }
+        match procinfo::sample(pid) {
+            Some(sample) => {
+                if self.usage.len() == 2 {
+                    self.usage.remove(0);
+                }
+                self.usage.push(sample);
+            }
+            // The process exited
+            None => self.usage.clear(),
+        }
+        self.dirty = true;
+    }
+
+    fn expire_message(&mut self) {
+        let expired = self
+            .last_message
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= MESSAGE_DURATION);
+        if expired {
+            self.last_message = None;
+            self.dirty = true;
+        }
+    }
+
+    fn receive_stdin_ports(&mut self) {
+        let Some(receiver) = &self.stdin_ports else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok(Ok(port)) => {
+                    if !self.watch_ports.contains(&port) {
+                        self.watch_ports.push(port);
+                        self.dirty = true;
+                    }
+                }
+                Ok(Err(line)) => {
+                    self.status = Some(format!("Ignoring `{line}` from stdin, not a port."));
+                    self.dirty = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.stdin_ports = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Stop highlighting changes after a while, and remove the gone processes then.
+    fn expire_changes(&mut self) {
+        let expired = self
+            .changes
+            .iter()
+            .filter(|(_, (_, since))| since.elapsed() >= CHANGE_DURATION)
+            .map(|(&pid, &(change, _))| (pid, change))
+            .collect_vec();
+        if expired.is_empty() {
+            return;
+        }
+        let previous = self.selection();
+        for (pid, change) in expired {
+            self.changes.remove(&pid);
+            if change == Change::Gone {
+                self.processes.retain(|p| p.pid != pid);
+                self.filter_cache.get_mut().key = None;
+            }
+        }
+        self.restore_selection(previous);
+        self.dirty = true;
+    }
+
+    /// Remember which processes of a scan are new, and keep the ones that are gone
+    /// in the list for a moment. Returns the new list.
+    fn track_changes(&mut self, mut processes: Vec<Process>) -> Vec<Process> {
+        let now = Instant::now();
+        for process in &processes {
+            let known = self.processes.iter().any(|p| p.pid == process.pid);
+            let gone = matches!(self.changes.get(&process.pid), Some((Change::Gone, _)));
+            if !known || gone {
+                self.changes.insert(process.pid, (Change::Appeared, now));
+            }
+        }
+        for old in &self.processes {
+            if processes.iter().any(|p| p.pid == old.pid) {
+                continue;
+            }
+            if !matches!(self.changes.get(&old.pid), Some((Change::Gone, _))) {
+                self.changes.insert(old.pid, (Change::Gone, now));
+            }
+            processes.push(old.clone());
+        }
+        processes
+    }
+
+    /// Jump to the typed row once no more digits follow.
+    fn expire_count(&mut self) {
+        if let Some((count, typed)) = self.count
+            && typed.elapsed() >= COUNT_TIMEOUT
+        {
+            self.count = None;
+            self.jump_to_row(count);
+            self.dirty = true;
+        }
+    }
+
+    /// Select the visible row with this number, counting from 1.
+    fn jump_to_row(&mut self, row: usize) {
+        let rows = self.filtered_list().count();
+        if rows > 0 {
+            self.table.select(Some(row.clamp(1, rows) - 1));
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    fn set_processes(&mut self, scan: io::Result<Scan>) {
+        // To keep a stable selection, we will remember the selected process
+        // before updating and restore it after.
+        let previous = self.selection();
+        // The uptimes change with every scan
+        self.dirty |= self.scanning || self.scan_options().uptime;
+        // Everything is new in the first scan, that is not worth a highlight.
+        let first_scan = std::mem::replace(&mut self.scanning, false);
+
+        match scan {
+            Ok(scan) => {
+                self.count_connections(&scan.processes);
+                self.dirty |= scan.connection_counts != self.port_connections;
+                self.port_connections = scan.connection_counts;
+                // Gone processes do not share a port with the ones that replace them.
+                self.shared_ports = shared_ports(&scan.processes);
+                let processes = match first_scan {
+                    true => scan.processes,
+                    false => self.track_changes(scan.processes),
+                };
+                // Most scans find the same processes as the last one, keep the filtered list then.
+                if processes != self.processes {
+                    self.processes = processes;
+                    self.filter_cache.get_mut().key = None;
+                    self.dirty = true;
+                }
+                self.dirty |= scan.warnings != self.warnings || self.error.is_some();
+                self.warnings = scan.warnings;
+                self.error = None;
+                // PIDs are reused, forget about processes that are gone.
+                let processes = &self.processes;
+                let exists = |pid: &usize| processes.iter().any(|p| p.pid == *pid);
+                self.command_lines.retain(|pid, _| exists(pid));
+                self.marked.retain(exists);
+                self.update_parents();
+            }
+            Err(e) => {
+                let error = Some(e.to_string());
+                self.dirty |= error != self.error;
+                self.error = error;
+            }
+        }
+
+        self.restore_selection(previous);
+    }
+
+    /// Remember how many connections each process has, for the trend in the details.
+    /// Processes that are gone are forgotten.
+    fn count_connections(&mut self, processes: &[Process]) {
+        if !self.scan_options().connections {
+            self.connection_counts.clear();
+            return;
+        }
+        let mut counts = HashMap::new();
+        for process in processes {
+            let mut history = self
+                .connection_counts
+                .remove(&process.pid)
+                .unwrap_or_default();
+            if history.len() == CONNECTION_HISTORY {
+                history.pop_front();
+            }
+            let connections = process.ports.iter().filter(|s| s.remote.is_some()).count();
+            history.push_back(connections as u64);
+            counts.insert(process.pid, history);
         }
+        self.connection_counts = counts;
+    }
+
+    /// The PID and row of the selected process.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let count = self.filtered_list().count();
+        let i = self.table.selected()?.min(count.checked_sub(1)?);
+        let process = self.filtered_list().nth(i)?;
+        Some((process.pid, i))
+    }
 
-        if let Some(selected_pid) = selected_pid {
-            let i = self.filtered_list().position(|p| p.pid == selected_pid);
-            self.table.select(i);
+    /// Select the previously selected process again after the list changed,
+    /// for refreshes, filtering and sorting alike. The selected row stays at the same
+    /// height on the screen, so the list doesn't jump around it.
+    fn restore_selection(&mut self, previous: Option<(usize, usize)>) {
+        let pids = self.filtered_list().map(|p| p.pid).collect_vec();
+        let screen_row = previous.map_or(0, |(_, i)| i.saturating_sub(self.table.offset()));
+        let selected = restored_selection(previous, &pids);
+        self.table.select(selected);
+        if let Some(i) = selected {
+            *self.table.offset_mut() = i.saturating_sub(screen_row);
         }
     }
 
@@ -104,18 +1126,41 @@ impl App {
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
-        let event_available = event::poll(UPDATE_INTERVAL)?;
+        // Don't let the first scan or a typed row number wait for a key press.
+        let timeout = if self.scanning || self.count.is_some() || self.restart.is_some() {
+            FIRST_SCAN_POLL
+        } else {
+            self.update_interval
+        };
+        let event_available = event::poll(timeout)?;
         if !event_available {
             return Ok(());
         }
 
-        match event::read()? {
+        self.handle_event(event::read()?);
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        // Filtering and sorting change the rows, keep the selection on the same process then.
+        let pids = self.filtered_list().map(|p| p.pid).collect_vec();
+        let previous = self.selection();
+
+        match event {
+            // Key presses and resizes almost always change what is shown,
+            // but most mouse events are moves that change nothing.
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
+                self.dirty = true;
+                self.handle_key_event(key_event);
             }
+            Event::Mouse(mouse_event) => self.dirty |= self.handle_mouse_event(mouse_event),
+            Event::Resize(..) => self.dirty = true,
             _ => {}
         };
-        Ok(())
+
+        if !self.filtered_list().map(|p| p.pid).eq(pids) {
+            self.restore_selection(previous);
+        }
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
@@ -127,148 +1172,1083 @@ impl App {
             return;
         }
 
+        self.status = None;
+
+        // Nothing works without a process list, only allow quitting.
+        if self.error.is_some() {
+            if matches!(
+                key_event.code,
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')
+            ) {
+                self.exit();
+            }
+            return;
+        }
+
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
         match &mut self.state {
-            AppState::ShowList => match key_event.code {
-                KeyCode::Esc => self.handle_escape(),
-                KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
-                KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
-                KeyCode::Char('?') => self.state = AppState::ShowHelp,
-                KeyCode::Char('/') => self.state = AppState::EditFilter(self.filter.clone()),
-                KeyCode::Char('x') => self.kill_selected(),
+            AppState::ShowList if key_event.code == KeyCode::Esc => {
+                self.pending_keys.clear();
+                // Esc cancels a typed row number first.
+                if self.count.take().is_none() {
+                    self.handle_escape();
+                }
+            }
+            AppState::ShowList => self.handle_list_key(key_event),
+            AppState::ConfirmKill(targets, signal) => {
+                let (targets, signal) = (std::mem::take(targets), *signal);
+                self.state = AppState::ShowList;
+                if key_event.code == KeyCode::Char('y') {
+                    self.send_signal(&targets, signal);
+                }
+            }
+            AppState::ConfirmSudo(targets, signal) => {
+                let (targets, signal) = (std::mem::take(targets), *signal);
+                self.state = AppState::ShowList;
+                if key_event.code == KeyCode::Char('y') {
+                    self.sudo_retry = Some((targets, signal));
+                }
+            }
+            AppState::ConfirmRestart(pid) => {
+                let pid = *pid;
+                self.state = AppState::ShowList;
+                if key_event.code == KeyCode::Char('y') {
+                    self.restart(pid);
+                }
+            }
+            AppState::ConfirmQuit => match key_event.code {
+                KeyCode::Char('y' | 'q') => self.exit(),
+                _ => self.state = AppState::ShowList,
+            },
+            AppState::Detail(_) => match key_event.code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('h') => {
+                    self.state = AppState::ShowList
+                }
                 _ => {}
             },
-            AppState::ShowHelp => match key_event.code {
+            AppState::PickSignal(list) => match key_event.code {
+                KeyCode::Esc => self.state = AppState::ShowList,
+                KeyCode::Up | KeyCode::Char('k') => list.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => list.select_next(),
+                KeyCode::Enter => {
+                    let signal = list.selected().and_then(|i| Signal::iter().nth(i));
+                    self.state = AppState::ShowList;
+                    if let Some(signal) = signal {
+                        self.kill_selected(signal, false);
+                    }
+                }
+                _ => {}
+            },
+            // Scrolling too far is undone when the help is rendered
+            AppState::ShowHelp(offset) => match key_event.code {
                 KeyCode::Esc | KeyCode::Char('?') => self.state = AppState::ShowList,
+                KeyCode::Down | KeyCode::Char('j') => *offset += 1,
+                KeyCode::Up | KeyCode::Char('k') => *offset = offset.saturating_sub(1),
+                KeyCode::PageDown => *offset += self.help_page,
+                KeyCode::PageUp => *offset = offset.saturating_sub(self.help_page),
+                KeyCode::Home | KeyCode::Char('g') => *offset = 0,
+                KeyCode::End | KeyCode::Char('G') => *offset = usize::MAX,
                 _ => {}
             },
             AppState::EditFilter(filter) => match key_event.code {
                 KeyCode::Enter => {
                     self.filter = filter.clone();
                     self.state = AppState::ShowList;
+                    if let Err(e) = self.filter_history.push(&self.filter) {
+                        self.status = Some(format!("Could not save filter history: {e}."));
+                    }
+                }
+                KeyCode::Esc => {
+                    self.filter_history.reset();
+                    self.state = AppState::ShowList;
+                }
+                KeyCode::Up => {
+                    if let Some(previous) = self.filter_history.previous(filter) {
+                        *filter = previous.to_string();
+                    }
+                }
+                KeyCode::Down => {
+                    if let Some(next) = self.filter_history.next() {
+                        *filter = next.to_string();
+                    }
                 }
-                KeyCode::Esc => self.state = AppState::ShowList,
                 KeyCode::Backspace => {
                     filter.pop();
                 }
+                KeyCode::Char('w') if control => delete_word(filter),
+                KeyCode::Char('u') if control => filter.clear(),
+                _ if control => {}
                 key => edit_filter_text(filter, key),
             },
+            AppState::Command(command) => match key_event.code {
+                KeyCode::Enter => {
+                    let command = std::mem::take(command);
+                    self.state = AppState::ShowList;
+                    self.run_command(&command);
+                }
+                KeyCode::Esc => self.state = AppState::ShowList,
+                // Like in vim, deleting the `:` cancels
+                KeyCode::Backspace if command.is_empty() => self.state = AppState::ShowList,
+                KeyCode::Backspace => {
+                    command.pop();
+                }
+                KeyCode::Char('w') if control => delete_word(command),
+                KeyCode::Char('u') if control => command.clear(),
+                _ if control => {}
+                key => edit_filter_text(command, key),
+            },
         }
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
-    }
-
-    fn render_process_table(&mut self, area: Rect, buf: &mut Buffer) {
-        let mut title = vec![format!(" 🔮 portwitch {VERSION} ").bold()];
+    fn perform(&mut self, action: Action) {
+        if self.read_only && action.kills() {
+            self.status = Some(READ_ONLY.to_string());
+            return;
+        }
+        match action {
+            Action::SelectPrevious => self.table.select_previous(),
+            Action::SelectNext => self.table.select_next(),
+            Action::PageUp => self.table.scroll_up_by(self.page_size()),
+            Action::PageDown => self.table.scroll_down_by(self.page_size()),
+            Action::HalfPageUp => self.table.scroll_up_by(self.page_size() / 2),
+            Action::HalfPageDown => self.table.scroll_down_by(self.page_size() / 2),
+            Action::SelectFirst => self.table.select_first(),
+            Action::SelectLast => self.table.select_last(),
+            Action::Quit => self.quit(),
+            Action::Help => self.state = AppState::ShowHelp(0),
+            Action::Filter => self.state = AppState::EditFilter(self.filter.clone()),
+            Action::Command => self.state = AppState::Command(String::new()),
+            Action::Sort => self.sort = self.sort.next(),
+            Action::ReverseSort => self.sort_reversed = !self.sort_reversed,
+            Action::FilterMode => self.filter_mode = self.filter_mode.next(),
+            Action::Connections => self.toggle_connections(),
+            Action::Pause => self.toggle_pause(),
+            Action::PublicOnly => self.public_only = !self.public_only,
+            Action::Mine if procinfo::current_uid().is_none() => {
+                self.status = Some("The owners of processes are not known here.".to_string())
+            }
+            Action::Mine => self.mine = !self.mine,
+            Action::Tree => self.toggle_tree(),
+            Action::SlowerUpdates => self.change_interval(true),
+            Action::FasterUpdates => self.change_interval(false),
+            Action::Refresh => self.force_refresh(),
+            Action::Kill => self.kill_selected(self.kill_signal, false),
+            Action::ForceKill => self.kill_selected(Signal::Kill, false),
+            Action::KillGroup => self.kill_selected(self.kill_signal, true),
+            Action::PickSignal => {
+                self.state = AppState::PickSignal(ListState::default().with_selected(Some(0)))
+            }
+            Action::Restart => self.restart_selected(),
+            Action::Detail => self.show_detail(),
+            Action::Mark => self.toggle_mark(),
+            Action::OpenFiles => self.page_open_files = self.selected_process().map(|p| p.pid),
+            Action::KillLog => self.show_kill_log = !self.show_kill_log,
+            Action::ServiceNames => self.service_names = !self.service_names,
+            Action::Compact => self.compact = !self.compact,
+            Action::YankPid => self.yank_selected(|p| p.pid.to_string()),
+            Action::YankKill => {
+                let signal = self.kill_signal;
+                self.yank_selected(|p| kill::shell_command(Target::Process(p.pid), signal))
+            }
+            Action::YankPorts => {
+                self.yank_selected(|p| p.ports.iter().map(|s| s.port).unique().join(","))
+            }
+            Action::OpenBrowser => self.open_in_browser(),
+        }
+    }
 
-        match &self.state {
-            AppState::ShowList | AppState::ShowHelp if !self.filter.is_empty() => {
-                title.push(format!("/{}", self.filter).light_blue());
+    /// Returns whether the event was used, and the screen needs drawing again.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) -> bool {
+        if !matches!(self.state, AppState::ShowList) || self.error.is_some() {
+            return false;
+        }
+
+        let position = Position::new(mouse_event.column, mouse_event.row);
+        match mouse_event.kind {
+            MouseEventKind::ScrollUp => self.table.select_previous(),
+            MouseEventKind::ScrollDown => self.table.select_next(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let layout = &self.table_layout;
+                if layout.header.contains(position) {
+                    let key = layout
+                        .columns
+                        .iter()
+                        .find(|(area, _)| area.x <= position.x && position.x < area.right())
+                        .and_then(|(_, key)| *key);
+                    if let Some(key) = key {
+                        self.sort_by(key);
+                    }
+                } else if layout.rows.contains(position) {
+                    let i = self.table.offset() + (position.y - layout.rows.y) as usize;
+                    if i < self.filtered_list().count() {
+                        self.table.select(Some(i));
+                    }
+                }
             }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The number of rows that fit on the screen.
+    fn page_size(&self) -> u16 {
+        self.table_layout.rows.height.max(1)
+    }
+
+    /// Sort by the key, or reverse the direction if already sorted by it.
+    fn sort_by(&mut self, key: SortKey) {
+        if self.sort == key {
+            self.sort_reversed = !self.sort_reversed;
+        } else {
+            self.sort = key;
+            self.sort_reversed = false;
+        }
+    }
+
+    /// Exit, after asking if the config wants that.
+    fn quit(&mut self) {
+        if self.confirm_quit {
+            self.state = AppState::ConfirmQuit;
+        } else {
+            self.exit();
+        }
+    }
+
+    fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    fn render_process_table(&mut self, area: Rect, buf: &mut Buffer) {
+        let mut title = vec![format!(" 🔮 portwitch {VERSION} ").bold()];
+
+        let theme = self.theme.clone();
+        let filter_style = if self.current_filter().is_invalid() {
+            theme.invalid_filter
+        } else {
+            theme.filter
+        };
+        match &self.state {
             AppState::EditFilter(filter) => {
-                title.push(format!("/{filter}").black().on_light_blue());
+                title.push(Span::styled(format!("/{filter}"), filter_style.reversed()));
+            }
+            _ if !self.filter.is_empty() => {
+                title.push(Span::styled(format!("/{}", self.filter), filter_style));
             }
             _ => (),
         }
 
+        if self.filter_mode != FilterMode::default() {
+            title.push(Span::styled(
+                format!(" ({})", self.filter_mode),
+                theme.filter,
+            ));
+        }
+
+        if self.scanning {
+            title.push(Span::styled(" scanning… ", theme.badge));
+        }
+
+        if self.paused {
+            title.push(Span::styled(" paused ", theme.badge));
+        }
+
+        if self.updates_stopped() {
+            title.push(Span::styled(
+                " ⚠ updates stopped, the scan keeps crashing ",
+                theme.warning,
+            ));
+        }
+
+        if self.public_only {
+            title.push(Span::styled(" public ", theme.badge));
+        }
+
+        if self.mine {
+            title.push(Span::styled(" mine ", theme.badge));
+        }
+
+        if self.read_only {
+            title.push(Span::styled(" read-only ", theme.badge));
+        }
+
+        if let Some(root) = self.tree {
+            title.push(Span::styled(format!(" tree {root} "), theme.badge));
+        }
+
+        if let Some(bind) = &self.bind {
+            title.push(Span::styled(format!(" on {} ", bind.name), theme.badge));
+        }
+
+        if let Some(range) = &self.port_range {
+            let badge = format!(" ports {}-{} ", range.start(), range.end());
+            title.push(Span::styled(badge, theme.badge));
+        }
+
+        if let Some(warning) = self.warnings.first() {
+            title.push(Span::styled(format!(" ⚠ {warning} "), theme.warning));
+        }
+
+        let ScanOptions {
+            connections,
+            uptime,
+            protocol,
+            count_connections,
+        } = self.scan_options();
+        if let Some(protocol) = protocol {
+            title.push(Span::styled(format!(" {protocol} "), theme.badge));
+        }
+        if connections {
+            title.push(Span::styled(" connections ", theme.badge));
+        }
+
+        let count = self.filtered_list().count();
+        let position = match self.table.selected() {
+            Some(i) if count > 0 => format!(" {}/{count} ", (i + 1).min(count)),
+            _ => format!(" {count} "),
+        };
+
         let title = Line::from(title);
-        let block = Block::new()
-            .title(title.centered())
-            .title_bottom(self.bottom_title())
-            .style(Style::new().white());
+        let block = if self.compact {
+            // Only a line for what is typed or the last message, when there is one
+            let prompt = match &self.state {
+                AppState::EditFilter(filter) => {
+                    Some(Span::styled(format!("/{filter}"), filter_style.reversed()))
+                }
+                AppState::Command(command) => {
+                    Some(Span::styled(format!(":{command}"), theme.filter.reversed()))
+                }
+                _ => self
+                    .status
+                    .iter()
+                    .chain(self.last_message.as_ref().map(|(message, _)| message))
+                    .next()
+                    .map(|message| Span::styled(message.clone(), theme.accent)),
+            };
+            let block = Block::new().style(theme.text);
+            match prompt {
+                Some(prompt) => block.title_bottom(prompt),
+                None => block,
+            }
+        } else {
+            Block::new()
+                .title(title.centered())
+                .title_top(Line::from(position).right_aligned())
+                .title_bottom(self.bottom_title())
+                .title_bottom(Line::from(self.summary()).right_aligned())
+                .title_bottom(
+                    Line::from(format!(" ⟳ {} ", format_interval(self.update_interval)))
+                        .left_aligned(),
+                )
+                .style(theme.text)
+        };
 
-        let rows = self.filtered_list().map(|p| {
-            Row::new(vec![
-                format!("{:>5}", p.pid),
-                p.command.to_string(),
-                p.ports.join(","),
-            ])
-        });
+        let visible = self
+            .columns
+            .iter()
+            .copied()
+            .filter(|&column| match column {
+                // Compact rows are just `pid command ports`
+                _ if self.compact => {
+                    matches!(column, Column::Pid | Column::Command | Column::Ports)
+                }
+                Column::Uptime => uptime,
+                Column::Conns => count_connections,
+                Column::Remote => connections,
+                _ => true,
+            })
+            .collect_vec();
 
-        let header = Row::new(vec!["PID", "Command", "Ports"]).style(Style::new().bold());
+        let header_cell = |name: &str, key: Option<SortKey>| match key {
+            Some(key) if key == self.sort => {
+                let arrow = if self.sort_reversed { "▼" } else { "▲" };
+                format!("{name} {arrow}")
+            }
+            _ => name.to_string(),
+        };
+        let sort_key = |column| match column {
+            Column::Pid => Some(SortKey::Pid),
+            Column::Command => Some(SortKey::Command),
+            Column::Ports => Some(SortKey::Port),
+            Column::Address | Column::Conns | Column::Uptime | Column::Remote => None,
+        };
+        let columns = std::iter::once(("", None, Constraint::Length(1)))
+            .chain(
+                visible
+                    .iter()
+                    .map(|&column| (column.header(), sort_key(column), column.width())),
+            )
+            .collect_vec();
+        let header = columns.iter().map(|&(name, key, _)| header_cell(name, key));
+        let header = Row::new(header).style(Style::new().bold());
+        let widths = columns.iter().map(|&(_, _, width)| width).collect_vec();
 
-        let columns = [
-            Constraint::Length(8),
-            Constraint::Fill(1),
-            Constraint::Fill(1),
-        ];
+        // Remember where things are for mouse support. This mirrors the layout that Table uses.
+        let inner = block.inner(area);
+        let header_height = if self.compact { 0 } else { 1 };
+        let [header_area, rows_area] =
+            Layout::vertical([Constraint::Length(header_height), Constraint::Fill(1)]).areas(inner);
+        let symbol_width = Line::from(theme.highlight_symbol.as_str()).width() as u16;
+        let [_highlight_area, cells_area] =
+            Layout::horizontal([Constraint::Length(symbol_width), Constraint::Fill(0)])
+                .areas(inner);
+        let column_areas = Layout::horizontal(widths.clone())
+            .flex(Flex::Start)
+            .spacing(1)
+            .split(cells_area);
+        let table_layout = TableLayout {
+            header: header_area,
+            rows: rows_area,
+            columns: column_areas
+                .iter()
+                .zip(&columns)
+                .map(|(&area, &(_, key, _))| (area, key))
+                .collect(),
+        };
 
-        let table = Table::new(rows, columns)
-            .block(block)
-            .header(header)
-            .highlight_symbol(">")
+        let is_shared =
+            |s: &Socket| s.remote.is_none() && self.shared_ports.contains(&(s.protocol, s.port));
+        let rows = self.filtered_list().map(|p| {
+            let mark = if self.marked.contains(&p.pid) {
+                "✓"
+            } else {
+                ""
+            };
+            let cells = std::iter::once(Line::from(mark)).chain(
+                visible
+                    .iter()
+                    .zip(&column_areas[1..])
+                    .map(|(&column, area)| match column {
+                        Column::Command => self.command_cell(p, usize::from(area.width)),
+                        _ => Line::from(self.cell(column, p, usize::from(area.width))),
+                    }),
+            );
+            let watched = p.ports.iter().any(|s| self.watch_ports.contains(&s.port));
+            let style = if let Some((change, _)) = self.changes.get(&p.pid) {
+                match change {
+                    Change::Appeared => theme.appeared,
+                    Change::Gone => theme.gone,
+                }
+            } else if p.ports.iter().any(is_shared) {
+                theme.warning
+            } else if watched {
+                theme.watched
+            } else {
+                Style::new()
+            };
+            Row::new(cells).style(style)
+        });
+
+        let table = Table::new(rows, widths).block(block);
+        let table = if self.compact {
+            table
+        } else {
+            table.header(header)
+        };
+        let table = table
+            .highlight_symbol(theme.highlight_symbol.as_str())
             .highlight_spacing(HighlightSpacing::Always)
-            .row_highlight_style(Style::new().light_red().bold());
+            .row_highlight_style(theme.highlight);
 
+        self.table_layout = table_layout;
         StatefulWidget::render(table, area, buf, &mut self.table);
+
+        if count == 0 && !self.scanning && self.error.is_none() {
+            let filter = match &self.state {
+                AppState::EditFilter(f) => f,
+                _ => &self.filter,
+            };
+            let message = if self.processes.is_empty() {
+                "No listening processes found".to_string()
+            } else if !filter.is_empty() {
+                format!("No processes match '{filter}'")
+            } else if self.public_only {
+                "No public processes found".to_string()
+            } else if self.mine {
+                "No processes of yours found".to_string()
+            } else if let Some(root) = self.tree {
+                format!("No processes in the tree of {root}")
+            } else if let Some(bind) = &self.bind {
+                format!("No processes reachable on {}", bind.name)
+            } else {
+                "No processes in the port range".to_string()
+            };
+            let [message_area] = Layout::vertical([Constraint::Length(1)])
+                .flex(Flex::Center)
+                .areas(rows_area);
+            Line::from(message)
+                .centered()
+                .style(theme.text.dim())
+                .render(message_area, buf);
+        }
+    }
+
+    /// The command cell, with the characters matched by a fuzzy filter highlighted.
+    fn command_cell(&self, p: &Process, width: usize) -> Line<'static> {
+        let text = self.cell(Column::Command, p, width);
+        let cache = self.filter_cache.borrow();
+        let Some(positions) = cache.matched.get(&p.pid) else {
+            return Line::from(text);
+        };
+        let indent = self.indent(p.pid).chars().count();
+        let length = text.chars().count();
+        // The `…` of a truncated command is not part of it
+        let shown = match length < indent + p.command.chars().count() {
+            true => length.saturating_sub(indent + 1),
+            false => length - indent,
+        };
+        let is_matched = |i: usize| {
+            i >= indent && i - indent < shown && positions.binary_search(&(i - indent)).is_ok()
+        };
+        let spans = text
+            .chars()
+            .enumerate()
+            .chunk_by(|&(i, _)| is_matched(i))
+            .into_iter()
+            .map(|(matched, chars)| {
+                let chunk: String = chars.map(|(_, c)| c).collect();
+                match matched {
+                    true => Span::styled(chunk, self.theme.matched),
+                    false => Span::raw(chunk),
+                }
+            })
+            .collect_vec();
+        Line::from(spans)
     }
 
-    fn render_help(&self, area: Rect, buf: &mut Buffer) {
+    /// Children are indented below their parent in the process tree.
+    fn indent(&self, pid: usize) -> String {
+        match self.filter_cache.borrow().depths.get(&pid) {
+            Some(&depth) if depth > 0 => format!("{}└ ", "  ".repeat(depth - 1)),
+            _ => String::new(),
+        }
+    }
+
+    /// The text of a cell in the process list. Lists that are too wide end in `+N more`.
+    fn cell(&self, column: Column, p: &Process, width: usize) -> String {
+        match column {
+            Column::Pid => format!("{:>5}", p.pid),
+            Column::Command => {
+                let indent = self.indent(p.pid);
+                let width = width.saturating_sub(indent.chars().count());
+                format!("{indent}{}", truncate(&p.command, width))
+            }
+            Column::Address => {
+                let addresses = p.ports.iter().map(|s| s.address.clone()).unique();
+                fit_list(&addresses.collect_vec(), width)
+            }
+            Column::Ports => {
+                let ports = |service_names: bool| {
+                    let ports = p.ports.iter().map(|s| {
+                        let shared =
+                            s.remote.is_none() && self.shared_ports.contains(&(s.protocol, s.port));
+                        let warning = if shared { "⚠" } else { "" };
+                        // A connection's local port says nothing about the service
+                        let service = match s.remote {
+                            None if service_names => services::name(s.protocol, s.port),
+                            _ => None,
+                        };
+                        match service {
+                            Some(name) => format!("{warning}{s} ({name})"),
+                            None => format!("{warning}{s}"),
+                        }
+                    });
+                    ports.collect_vec()
+                };
+                // Ports are more important than their names, leave out the names if they don't fit
+                let named = ports(self.service_names);
+                if named.join(",").chars().count() <= width {
+                    named.join(",")
+                } else {
+                    fit_list(&ports(false), width)
+                }
+            }
+            Column::Conns => {
+                let listeners = p.ports.iter().filter(|s| s.remote.is_none());
+                let count: usize = listeners
+                    .map(|s| (s.protocol, s.port))
+                    .unique()
+                    .filter_map(|key| self.port_connections.get(&key))
+                    .sum();
+                format!("{count:>5}")
+            }
+            Column::Uptime => {
+                let elapsed = p.started.and_then(|started| started.elapsed().ok());
+                elapsed.map(procinfo::format_uptime).unwrap_or_default()
+            }
+            Column::Remote => {
+                let remotes = p.ports.iter().flat_map(|s| s.remote.clone()).unique();
+                fit_list(&remotes.collect_vec(), width)
+            }
+        }
+    }
+
+    /// The newest kills that fit, like `12:03:11 sent SIGTERM to node (4821) on :3000`.
+    fn render_kill_log(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(Line::from(" Kill log ".bold()).centered())
+            .style(self.theme.text);
+        let height = usize::from(block.inner(area).height);
+        let now = (Instant::now(), SystemTime::now());
+        let lines = self.kill_log[self.kill_log.len().saturating_sub(height)..]
+            .iter()
+            .map(|(killed, entry)| {
+                let time = now.1 - now.0.duration_since(*killed);
+                Line::from(vec![
+                    procinfo::format_clock(time).bold(),
+                    format!(" {entry}").into(),
+                ])
+            })
+            .collect_vec();
+        let lines = if lines.is_empty() {
+            vec![Line::from("Nothing killed yet".italic())]
+        } else {
+            lines
+        };
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+
+    fn render_help(&mut self, area: Rect, buf: &mut Buffer) {
         let title = Line::from(" Help ".bold());
-        let items = [
+        let line = |mut keys: Vec<Span<'static>>, description: &str| {
+            keys.push(format!(" {description}").into());
+            Line::from(keys)
+        };
+        let pair = |first, second| {
+            vec![
+                self.first_key(first).bold(),
+                " / ".into(),
+                self.first_key(second).bold(),
+            ]
+        };
+        let mut quit = self.key_spans(Action::Quit);
+        quit.extend([" or ".into(), "<^c>".bold()]);
+
+        let mut items = vec![
             Line::from(vec![
                 "<esc>".bold(),
-                " Clear filter / close help / quit".into(),
+                " Clear marks, then filter, then quit".into(),
             ]),
+            line(quit, "Quit"),
+            line(self.key_spans(Action::SelectPrevious), "Select previous"),
+            line(self.key_spans(Action::SelectNext), "Select next"),
+            line(pair(Action::PageUp, Action::PageDown), "Scroll a page"),
+            line(
+                pair(Action::HalfPageUp, Action::HalfPageDown),
+                "Scroll half a page",
+            ),
+            line(
+                pair(Action::SelectFirst, Action::SelectLast),
+                "Jump to top / bottom",
+            ),
             Line::from(vec![
-                "<k>".bold(),
-                " or ".into(),
-                "<↑>".bold(),
-                " Select previous".into(),
+                "<1-9>".bold(),
+                " Jump to that row, or move that many with ".into(),
+                self.first_key(Action::SelectNext).bold(),
+                " / ".into(),
+                self.first_key(Action::SelectPrevious).bold(),
             ]),
+        ];
+        if !self.read_only {
+            items.extend([
+                line(self.key_spans(Action::Mark), "Mark for killing together"),
+                line(self.key_spans(Action::Kill), "Kill selected or marked"),
+                line(
+                    self.key_spans(Action::ForceKill),
+                    "Kill selected with SIGKILL",
+                ),
+                line(
+                    self.key_spans(Action::PickSignal),
+                    "Kill selected with signal…",
+                ),
+                line(
+                    self.key_spans(Action::KillGroup),
+                    "Kill process group, including children",
+                ),
+                format!(
+                    "    that {} would leave running",
+                    self.first_key(Action::Kill)
+                )
+                .into(),
+                line(
+                    self.key_spans(Action::Restart),
+                    "Restart selected with the same command",
+                ),
+            ]);
+        }
+        items.extend([
+            line(self.key_spans(Action::Detail), "Show details"),
+            line(
+                self.key_spans(Action::OpenFiles),
+                "Show open files in $PAGER",
+            ),
+            line(self.key_spans(Action::YankPid), "Copy PID"),
+            line(self.key_spans(Action::YankPorts), "Copy ports"),
+            line(
+                self.key_spans(Action::YankKill),
+                "Copy a command that kills selected",
+            ),
+            line(
+                self.key_spans(Action::OpenBrowser),
+                "Open web port in the browser",
+            ),
+            line(self.key_spans(Action::Filter), "Filter"),
             Line::from(vec![
-                "<j>".bold(),
-                " or ".into(),
+                "    <↑>".bold(),
+                " / ".into(),
                 "<↓>".bold(),
-                " Select next".into(),
+                " previous filters, ".into(),
+                "<^w>".bold(),
+                " / ".into(),
+                "<^u>".bold(),
+                " delete word / all".into(),
+            ]),
+            line(
+                self.key_spans(Action::Command),
+                "Run a command, like `kill 8080`",
+            ),
+            line(
+                self.key_spans(Action::FilterMode),
+                "Cycle filter mode (fuzzy, regex)",
+            ),
+            line(
+                self.key_spans(Action::Connections),
+                "Toggle established connections",
+            ),
+            line(self.key_spans(Action::Pause), "Pause updates"),
+            line(self.key_spans(Action::SlowerUpdates), "Update less often"),
+            line(self.key_spans(Action::FasterUpdates), "Update more often"),
+            line(
+                self.key_spans(Action::PublicOnly),
+                "Only show ports reachable from the network",
+            ),
+            line(self.key_spans(Action::Mine), "Only show my processes"),
+            line(
+                self.key_spans(Action::Tree),
+                "Only show the process and its children",
+            ),
+            line(self.key_spans(Action::Refresh), "Refresh now"),
+            line(self.key_spans(Action::KillLog), "Toggle the log of kills"),
+            line(
+                self.key_spans(Action::ServiceNames),
+                "Toggle service names like https",
+            ),
+            line(
+                self.key_spans(Action::Compact),
+                "Toggle compact rows without titles",
+            ),
+            line(self.key_spans(Action::Sort), "Cycle sort column"),
+            line(
+                self.key_spans(Action::ReverseSort),
+                "Reverse sort direction",
+            ),
+            Line::from(vec![
+                "<click>".bold(),
+                " Select row / sort by column".into(),
             ]),
-            Line::from(vec!["<x>".bold(), " Kill selected".into()]),
-            Line::from(vec!["</>".bold(), " Filter".into()]),
             "".into(),
             Line::from(vec![
-                "Pro-Tip".yellow(),
+                Span::styled("Pro-Tip", self.theme.accent),
                 ": portwitch accepts CLI args".into(),
             ]),
             "  to set an initial filter".into(),
-            Line::from(vec!["  $ portwitch ".into(), "8080".yellow()]),
+            Line::from(vec![
+                "  $ portwitch ".into(),
+                Span::styled("8080", self.theme.accent),
+            ]),
+        ]);
+
+        // On a small terminal only a part fits, the rest is scrolled to
+        let fit = usize::from(area.height.saturating_sub(4)).max(1);
+        self.help_page = fit;
+        if let AppState::ShowHelp(offset) = &mut self.state {
+            *offset = (*offset).min(items.len().saturating_sub(fit));
+            render_scrolled_popup(title, items, *offset, Style::new(), area, buf);
+        }
+    }
+
+    fn render_detail(&self, pid: usize, area: Rect, buf: &mut Buffer) {
+        let process = self.processes.iter().find(|p| p.pid == pid);
+        let title = match process {
+            Some(process) => format!(" {} ({pid}) ", process.command),
+            None => format!(" {pid} "),
+        };
+
+        // The row of the connection counts and where the sparkline starts in it
+        let mut sparkline = None;
+        let items = match process {
+            Some(process) => {
+                let header = Line::from(vec![
+                    format!("{:<6}", "Proto").bold(),
+                    format!("{:<5}", "FD").bold(),
+                    format!("{:<9}", "Inode").bold(),
+                    "Address".bold(),
+                ]);
+                let sockets = process.ports.iter().map(|socket| {
+                    let fd = socket.fd.as_deref().unwrap_or("-");
+                    let inode = socket.inode.as_deref().unwrap_or("-");
+                    let service = match socket.remote {
+                        None => services::name(socket.protocol, socket.port),
+                        Some(_) => None,
+                    };
+                    let service = service.map(|name| format!(" ({name})")).unwrap_or_default();
+                    Line::from(format!(
+                        "{:<6}{fd:<5}{inode:<9}{}{service}",
+                        socket.kind(),
+                        socket.network()
+                    ))
+                });
+                let command_line = match self.command_lines.get(&pid) {
+                    Some(Some(command_line)) => command_line.clone().into(),
+                    _ => process.command.clone().italic(),
+                };
+                let cpu = match self.usage.as_slice() {
+                    [earlier, latest] => format!("{:.1}%", latest.cpu_percent(earlier)),
+                    _ => "…".to_string(),
+                };
+                let memory = match self.usage.last() {
+                    Some(sample) => procinfo::format_bytes(sample.memory),
+                    None => "…".to_string(),
+                };
+                let mut usage = Line::from(vec![
+                    "CPU ".bold(),
+                    cpu.into(),
+                    "  Memory ".bold(),
+                    memory.into(),
+                ]);
+                if let Some(ppid) = process.ppid {
+                    usage.push_span("  Parent ".bold());
+                    usage.push_span(ppid.to_string());
+                }
+                let mut lines = vec![Line::from(command_line), usage];
+                match &self.containers {
+                    Some(Ok(containers)) => {
+                        let names = containers
+                            .iter()
+                            .filter(|c| process.ports.iter().any(|s| c.ports.contains(&s.port)))
+                            .map(|c| &c.name)
+                            .join(", ");
+                        let names = if names.is_empty() {
+                            "none found".italic()
+                        } else {
+                            names.into()
+                        };
+                        lines.push(Line::from(vec!["Container ".bold(), names]));
+                    }
+                    Some(Err(e)) => {
+                        lines.push(Line::from(vec!["Container ".bold(), e.clone().italic()]))
+                    }
+                    None => {}
+                }
+                // Room for a sparkline, drawn over the line once the popup is placed
+                if let Some(counts) = self.connection_counts.get(&pid) {
+                    let label = format!("Connections {:<4}", counts.back().unwrap_or(&0));
+                    sparkline = Some((lines.len(), label.len() as u16));
+                    let room = " ".repeat(CONNECTION_HISTORY);
+                    lines.push(Line::from(vec![label.bold(), room.into()]));
+                }
+                lines.extend([Line::default(), header]);
+                lines.into_iter().chain(sockets).collect()
+            }
+            None => vec![Line::from("The process has exited.")],
+        };
+
+        let inner = render_popup(Line::from(title.bold()), items, Style::new(), area, buf);
+        if let Some((row, label_width)) = sparkline
+            && let Some(counts) = self.connection_counts.get(&pid)
+            && (row as u16) < inner.height
+        {
+            let area = Rect {
+                x: inner.x + label_width,
+                y: inner.y + row as u16,
+                width: (counts.len() as u16).min(inner.width.saturating_sub(label_width)),
+                height: 1,
+            };
+            let counts = counts.iter().copied().collect_vec();
+            // At least one, so no connections at all is an empty line and not a full one
+            let max = counts.iter().copied().max().unwrap_or(0).max(1);
+            Sparkline::default()
+                .data(&counts)
+                .max(max)
+                .style(self.theme.accent)
+                .render(area, buf);
+        }
+    }
+
+    fn render_confirm_kill(
+        &self,
+        targets: &[Target],
+        signal: Signal,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let title = Line::from(" Kill ".bold());
+        let name = |target: Target| self.describe(target, false);
+        let mut items = match targets {
+            [target] => vec![Line::from(vec![
+                "Send ".into(),
+                format!("SIG{signal}").bold(),
+                format!(" to {}?", name(*target)).into(),
+            ])],
+            _ => {
+                let question = Line::from(vec![
+                    "Send ".into(),
+                    format!("SIG{signal}").bold(),
+                    " to".into(),
+                ]);
+                let names = targets
+                    .iter()
+                    .map(|&t| Line::from(format!("  {}", name(t))));
+                std::iter::once(question).chain(names).collect()
+            }
+        };
+        items.extend([
+            "".into(),
+            Line::from(vec![
+                "<y>".bold(),
+                " yes ".into(),
+                "<n>".bold(),
+                " no".into(),
+            ]),
+        ]);
+        render_popup(title, items, Style::new(), area, buf);
+    }
+
+    fn render_error(&self, error: &str, area: Rect, buf: &mut Buffer) {
+        let title = Line::from(" Error ".bold());
+        let items = vec![
+            Line::from(error.to_string()),
+            "".into(),
+            Line::from(vec![
+                "<esc>".bold(),
+                " or ".into(),
+                "<q>".bold(),
+                " to quit".into(),
+            ]),
         ];
+        render_popup(title, items, self.theme.error, area, buf);
+    }
+
+    fn render_signal_picker(&mut self, area: Rect, buf: &mut Buffer) {
+        let AppState::PickSignal(state) = &mut self.state else {
+            return;
+        };
+
+        let title = Line::from(" Send signal ".bold());
+        let items: Vec<Line> = Signal::iter()
+            .map(|signal| Line::from(format!("SIG{signal}")))
+            .collect();
+
+        // Add border, padding and highlight symbol to width and height
+        let height = items.len() as u16 + 2;
+        let width = items.iter().map(|line| line.width() as u16).max().unwrap() + 6;
+        let width = width.max(title.width() as u16 + 2);
 
         let block = Block::bordered()
             .title(title.centered())
-            .padding(Padding::proportional(1))
+            .padding(Padding::horizontal(1))
             .border_set(border::ROUNDED);
 
-        // Add border and padding to width and height
-        let height = items.len() as u16 + 4;
-        let width = items.iter().map(|line| line.width() as u16).max().unwrap() + 6;
         let area = area.centered(Constraint::Length(width), Constraint::Length(height));
 
-        let list = List::new(items).block(block);
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol(self.theme.highlight_symbol.as_str())
+            .highlight_spacing(HighlightSpacing::Always)
+            .highlight_style(self.theme.highlight);
         Widget::render(Clear, area, buf);
-        Widget::render(list, area, buf);
+        StatefulWidget::render(list, area, buf, state);
+    }
+
+    /// The keys bound to an action, like `<k> or <↑>`.
+    fn key_spans(&self, action: Action) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        for key in self.keymap.keys(action) {
+            if !spans.is_empty() {
+                spans.push(" or ".into());
+            }
+            spans.push(format!("<{key}>").bold());
+        }
+        if spans.is_empty() {
+            spans.push("<unbound>".bold());
+        }
+        spans
+    }
+
+    /// The first key bound to an action, like `<x>`.
+    fn first_key(&self, action: Action) -> String {
+        match self.keymap.keys(action).first() {
+            Some(key) => format!("<{key}>"),
+            None => "<unbound>".to_string(),
+        }
     }
 
     /// Text that is rendered at the bottom of the table.
     fn bottom_title(&self) -> Line<'static> {
+        let fixed = |items: &[(&str, &'static str)]| {
+            items
+                .iter()
+                .map(|&(key, text)| (key.to_string(), text))
+                .collect_vec()
+        };
         let items = match self.state {
-            AppState::ShowList => vec![
-                if self.filter.is_empty() {
-                    ("<esc>", "to quit")
-                } else {
-                    ("<esc>", "clear filter")
-                },
-                ("<x>", "to kill"),
-                ("<?>", "for help"),
+            AppState::ShowList if !self.marked.is_empty() => vec![
+                ("<esc>".to_string(), "clear marks"),
+                (self.first_key(Action::Kill), "to kill marked"),
+                (self.first_key(Action::Help), "for help"),
             ],
-            AppState::ShowHelp => vec![("<esc>", "close help")],
+            AppState::ShowList => {
+                let mut items = vec![if self.filter.is_empty() {
+                    (self.first_key(Action::Quit), "to quit")
+                } else {
+                    ("<esc>".to_string(), "clear filter")
+                }];
+                if !self.read_only {
+                    items.push((self.first_key(Action::Kill), "to kill"));
+                }
+                items.push((self.first_key(Action::Help), "for help"));
+                items
+            }
+            AppState::PickSignal(_) => fixed(&[("<esc>", "cancel"), ("<enter>", "send signal")]),
+            AppState::ShowHelp(_) => fixed(&[("<esc>", "close help"), ("<j>/<k>", "scroll")]),
+            AppState::Detail(_) => fixed(&[("<esc>", "close details")]),
+            AppState::ConfirmKill(..) => fixed(&[("<y>", "kill"), ("<n>", "cancel")]),
+            AppState::ConfirmSudo(..) => fixed(&[("<y>", "retry with sudo"), ("<n>", "cancel")]),
+            AppState::ConfirmRestart(_) => fixed(&[("<y>", "restart"), ("<n>", "cancel")]),
+            AppState::ConfirmQuit => fixed(&[("<y>", "quit"), ("<n>", "cancel")]),
             AppState::EditFilter(_) => {
-                vec![("<esc>", "discard filter"), ("<enter>", "confirm filter")]
+                fixed(&[("<esc>", "discard filter"), ("<enter>", "confirm filter")])
             }
+            AppState::Command(_) => fixed(&[("<esc>", "cancel"), ("<enter>", "run")]),
         };
 
         let mut line = Line::default().centered();
 
+        if let Some(status) = &self.status {
+            line.push_span(Span::styled(status.clone(), self.theme.accent));
+            line.push_span(" ");
+        }
+
+        if let Some((message, _)) = &self.last_message {
+            line.push_span(Span::styled(message.clone(), self.theme.accent));
+            line.push_span(" ");
+        }
+
+        if let Some((count, _)) = self.count {
+            line.push_span(Span::styled(
+                format!("row {count}"),
+                self.theme.filter.reversed(),
+            ));
+            line.push_span(" ");
+        }
+
+        if let AppState::Command(command) = &self.state {
+            line.push_span(Span::styled(
+                format!(":{command}"),
+                self.theme.filter.reversed(),
+            ));
+            line.push_span(" ");
+        }
+
         for (key, text) in items {
             line.push_span(key.bold());
             line.push_span(" ");
@@ -279,50 +2259,724 @@ impl App {
         line
     }
 
-    fn kill_selected(&mut self) {
-        let Some(selected) = self.table.selected() else {
+    /// Socket counts of the listed processes, like ` 12 TCP, 3 UDP, 5 IPv6 `.
+    fn summary(&self) -> String {
+        let sockets = self.filtered_list().flat_map(|p| &p.ports).collect_vec();
+        let count = |f: fn(&&Socket) -> bool| sockets.iter().filter(|s| f(s)).count();
+        let tcp = count(|s| s.protocol == Protocol::Tcp);
+        let udp = count(|s| s.protocol == Protocol::Udp);
+        let ipv6 = count(|s| s.family == Family::V6);
+        format!(" {tcp} TCP, {udp} UDP, {ipv6} IPv6 ")
+    }
+
+    fn scan_options(&self) -> ScanOptions {
+        *self.scan.lock().unwrap()
+    }
+
+    /// Switch between listeners and all connections, and scan again so the list and its
+    /// columns change right away.
+    fn toggle_connections(&mut self) {
+        let connections = {
+            let mut scan = self.scan.lock().unwrap();
+            scan.connections = !scan.connections;
+            scan.connections
+        };
+        self.force_refresh();
+        self.status = Some(match connections {
+            true => "Showing listeners and established connections.".to_string(),
+            false => "Showing listeners only.".to_string(),
+        });
+    }
+
+    fn selected_process(&self) -> Option<&Process> {
+        let (_, i) = self.selection()?;
+        self.filtered_list().nth(i)
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(pid) = self.selected_process().map(|p| p.pid) else {
             return;
         };
+        if !self.marked.remove(&pid) {
+            self.marked.insert(pid);
+        }
+    }
 
-        let Some(selected) = self.filtered_list().nth(selected) else {
+    /// Send the signal to the marked processes, or the selected one if none are marked.
+    /// With `group`, the whole process groups of those get the signal.
+    fn kill_selected(&mut self, signal: Signal, group: bool) {
+        let processes = if self.marked.is_empty() {
+            self.selected_process().into_iter().collect_vec()
+        } else {
+            self.filtered_list()
+                .filter(|p| self.marked.contains(&p.pid))
+                .collect_vec()
+        };
+        if processes.is_empty() {
             return;
+        }
+
+        let mut targets = Vec::new();
+        for process in processes {
+            let target = if group {
+                match process.pgid {
+                    // Never signal the group of init
+                    Some(pgid) if pgid > 1 => Target::Group(pgid),
+                    _ => {
+                        self.status = Some(format!("No process group known for {}.", process.pid));
+                        return;
+                    }
+                }
+            } else {
+                Target::Process(process.pid)
+            };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+
+        if self.confirm_kill {
+            self.state = AppState::ConfirmKill(targets, signal);
+        } else {
+            self.send_signal(&targets, signal);
+        }
+    }
+
+    /// Run a command from the `:` prompt, reporting problems in the status line.
+    fn run_command(&mut self, text: &str) {
+        match Command::parse(text) {
+            Ok(Command::Kill { .. }) if self.read_only => self.status = Some(READ_ONLY.to_string()),
+            Ok(Command::Kill { numbers, signal }) => {
+                let mut targets = Vec::new();
+                for number in numbers {
+                    // A port if something listens on it, else a PID
+                    let listening = self
+                        .processes
+                        .iter()
+                        .filter(|p| {
+                            p.ports
+                                .iter()
+                                .any(|s| s.remote.is_none() && usize::from(s.port) == number)
+                        })
+                        .collect_vec();
+                    let found = if listening.is_empty() {
+                        self.processes.iter().filter(|p| p.pid == number).collect()
+                    } else {
+                        listening
+                    };
+                    if found.is_empty() {
+                        self.status = Some(format!("No process on port or with PID {number}."));
+                        return;
+                    }
+                    for process in found {
+                        let target = Target::Process(process.pid);
+                        if !targets.contains(&target) {
+                            targets.push(target);
+                        }
+                    }
+                }
+                let signal = signal.unwrap_or(self.kill_signal);
+                self.state = AppState::ConfirmKill(targets, signal);
+            }
+            Err(e) => self.status = Some(format!("Error: {e}.")),
+        }
+    }
+
+    fn send_signal(&mut self, targets: &[Target], signal: Signal) {
+        let mut failed = Vec::new();
+        for &target in targets {
+            let entry = match kill::kill(target, signal) {
+                Ok(()) => format!("sent SIG{signal} to {}", self.describe(target, true)),
+                Err(e) => {
+                    let entry = format!("could not kill {}: {e}", self.describe(target, false));
+                    failed.push((target, e));
+                    entry
+                }
+            };
+            self.kill_log.push((Instant::now(), entry));
+        }
+        let message = match (targets, failed.first()) {
+            ([target], None) => format!("Sent SIG{signal} to {target}."),
+            (_, None) => format!("Sent SIG{signal} to {} processes.", targets.len()),
+            (_, Some((target, e))) => {
+                let reason = match e {
+                    KillError::PermissionDenied => {
+                        format!("Permission denied killing {target} — try sudo.")
+                    }
+                    KillError::NotFound => format!("{target} has exited already."),
+                    KillError::Other(_) => format!("Could not kill {target}: {e}."),
+                };
+                match targets.len() - failed.len() {
+                    0 => reason,
+                    sent => format!("Sent SIG{signal} to {sent} processes. {reason}"),
+                }
+            }
         };
+        self.last_message = Some((message, Instant::now()));
+        self.marked.clear();
 
-        kill(selected.pid);
+        let denied = failed
+            .into_iter()
+            .filter(|(_, e)| *e == KillError::PermissionDenied)
+            .map(|(target, _)| target)
+            .collect_vec();
+        if cfg!(unix) && self.sudo && !denied.is_empty() {
+            self.state = AppState::ConfirmSudo(denied, signal);
+        }
         self.refresh_processes();
     }
 
+    /// Restart the selected process, after asking if kills are confirmed.
+    fn restart_selected(&mut self) {
+        let Some(process) = self.selected_process() else {
+            return;
+        };
+        let pid = process.pid;
+        if let Some(restart) = &self.restart {
+            self.status = Some(format!("Still waiting for {} to exit.", restart.name));
+            return;
+        }
+        if procinfo::arguments(pid).is_none() {
+            let name = self.describe(Target::Process(pid), false);
+            self.status = Some(format!(
+                "Can not restart {name}, its arguments are unknown."
+            ));
+            return;
+        }
+        if self.confirm_kill {
+            self.state = AppState::ConfirmRestart(pid);
+        } else {
+            self.restart(pid);
+        }
+    }
+
+    /// Kill the process. Its command starts again, in the same directory, once it has exited.
+    fn restart(&mut self, pid: usize) {
+        let name = self.describe(Target::Process(pid), false);
+        let Some(args) = procinfo::arguments(pid) else {
+            self.status = Some(format!(
+                "Can not restart {name}, its arguments are unknown."
+            ));
+            return;
+        };
+        let dir = procinfo::working_dir(pid);
+
+        if let Err(e) = kill::kill(Target::Process(pid), self.kill_signal) {
+            self.status = Some(format!("Could not kill {name}: {e}."));
+            return;
+        }
+        self.last_message = Some((format!("Waiting for {name} to exit…"), Instant::now()));
+        self.restart = Some(PendingRestart {
+            pid,
+            name,
+            args,
+            dir,
+            killed: Instant::now(),
+        });
+    }
+
+    /// Start the command of a restart once the old process is gone, which is when the
+    /// port is free. This waits across ticks, so the UI keeps running meanwhile.
+    fn continue_restart(&mut self) {
+        let Some(restart) = &self.restart else {
+            return;
+        };
+        let exited = procinfo::sample(restart.pid).is_none();
+        if !exited && restart.killed.elapsed() < RESTART_TIMEOUT {
+            return;
+        }
+        let Some(restart) = self.restart.take() else {
+            return;
+        };
+        let name = restart.name;
+        let message = if exited {
+            match spawn_detached(&restart.args, restart.dir) {
+                Ok(new_pid) => format!("Restarted {name} as {new_pid}."),
+                Err(e) => format!("Killed {name}, but could not start it again: {e}."),
+            }
+        } else {
+            format!(
+                "{name} did not exit within {}s, not restarted.",
+                RESTART_TIMEOUT.as_secs()
+            )
+        };
+        self.kill_log
+            .push((Instant::now(), message.trim_end_matches('.').to_string()));
+        self.last_message = Some((message, Instant::now()));
+        self.dirty = true;
+        self.force_refresh();
+    }
+
+    /// A target like `node (4821)`, with its ports like `node (4821) on :3000`.
+    fn describe(&self, target: Target, ports: bool) -> String {
+        match target {
+            Target::Process(pid) => match self.processes.iter().find(|p| p.pid == pid) {
+                Some(process) if ports && !process.ports.is_empty() => {
+                    let mut ports = process
+                        .ports
+                        .iter()
+                        .map(|s| format!(":{}", s.port))
+                        .unique();
+                    format!("{} ({pid}) on {}", process.command, ports.join(","))
+                }
+                Some(process) => format!("{} ({pid})", process.command),
+                None => pid.to_string(),
+            },
+            Target::Group(pgid) => format!("process group {pgid}"),
+        }
+    }
+
+    fn show_detail(&mut self) {
+        if let Some(process) = self.selected_process() {
+            let pid = process.pid;
+            let proxy = docker::is_proxy(&process.command);
+            self.command_lines
+                .entry(pid)
+                .or_insert_with(|| procinfo::command_line(pid));
+            self.usage.clear();
+            self.containers =
+                (self.docker && proxy).then(|| docker::containers().map_err(|e| e.to_string()));
+            self.state = AppState::Detail(pid);
+        }
+    }
+
+    /// Copy some text about the selected process to the clipboard.
+    fn yank_selected(&mut self, text: impl Fn(&Process) -> String) {
+        let Some(text) = self.selected_process().map(text) else {
+            return;
+        };
+
+        self.status = Some(match clipboard::copy(&text) {
+            Ok(()) => format!("Copied {text}."),
+            Err(e) => format!("Could not copy to clipboard: {e}."),
+        });
+    }
+
+    /// Open the first port of the selected process that looks like a web server.
+    fn open_in_browser(&mut self) {
+        let Some(process) = self.selected_process() else {
+            return;
+        };
+        let Some(socket) = process.ports.iter().find(|s| browser::is_web(s)) else {
+            self.status = Some(format!("{} has no web port.", process.command));
+            return;
+        };
+        let url = browser::url(socket);
+        self.status = Some(match browser::open(&url) {
+            Ok(()) => format!("Opened {url}."),
+            Err(e) => format!("Could not open {url}: {e}."),
+        });
+    }
+
+    fn handle_list_key(&mut self, key_event: KeyEvent) {
+        let key = Key::from(key_event);
+        // Digits that are not bound to anything type a row number, or a count like `5j`.
+        if let KeyCode::Char(c @ '0'..='9') = key_event.code
+            && !key_event.modifiers.contains(KeyModifiers::CONTROL)
+            && self.pending_keys.is_empty()
+            && self.keymap.lookup(&[key]) == Lookup::Unbound
+            && (c != '0' || self.count.is_some())
+        {
+            let digit = c as usize - '0' as usize;
+            let count = self.count.map_or(0, |(count, _)| count);
+            let count = count.saturating_mul(10).saturating_add(digit);
+            self.count = Some((count, Instant::now()));
+            return;
+        }
+        let count = self.count.take().map(|(count, _)| count);
+        if let (Some(row), KeyCode::Enter) = (count, key_event.code) {
+            self.jump_to_row(row);
+            return;
+        }
+
+        self.pending_keys.push(key);
+        match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                let by = count.map(|count| u16::try_from(count).unwrap_or(u16::MAX));
+                match (action, by) {
+                    (Action::SelectNext, Some(by)) => self.table.scroll_down_by(by),
+                    (Action::SelectPrevious, Some(by)) => self.table.scroll_up_by(by),
+                    _ => self.perform(action),
+                }
+            }
+            // Keep the count for the rest of the sequence.
+            Lookup::Pending => self.count = count.map(|count| (count, Instant::now())),
+            Lookup::Unbound => self.pending_keys.clear(),
+        }
+    }
+
     fn handle_escape(&mut self) {
-        if self.filter.is_empty() {
-            self.exit();
+        if !self.marked.is_empty() {
+            self.marked.clear();
+        } else if self.tree.is_some() {
+            self.tree = None;
+        } else if self.filter.is_empty() {
+            self.quit();
         } else {
             self.filter.clear();
         }
     }
 
-    fn filtered_list(&self) -> impl Iterator<Item = &Process> {
+    /// Show the tree of the selected process, or everything again.
+    fn toggle_tree(&mut self) {
+        self.tree = match self.tree {
+            Some(_) => None,
+            None => self.selection().map(|(pid, _)| pid),
+        };
+        self.update_parents();
+    }
+
+    /// Look up the ancestors of the processes up to the root of the tree, unless the source
+    /// reported them. Parents that are known already are kept, the others are forgotten.
+    fn update_parents(&mut self) {
+        let Some(root) = self.tree else {
+            self.parents.clear();
+            return;
+        };
+        let reported: HashMap<usize, usize> = self
+            .processes
+            .iter()
+            .filter_map(|p| Some((p.pid, p.ppid?)))
+            .collect();
+        let mut parents = HashMap::new();
+        for process in &self.processes {
+            let mut pid = process.pid;
+            while pid != root && pid > 1 && !parents.contains_key(&pid) {
+                let parent = match (reported.get(&pid), self.parents.get(&pid)) {
+                    (Some(&ppid), _) => Some(ppid),
+                    (None, Some(&parent)) => parent,
+                    (None, None) => procinfo::parent_pid(pid),
+                };
+                parents.insert(pid, parent);
+                match parent {
+                    Some(parent) => pid = parent,
+                    None => break,
+                }
+            }
+        }
+        self.parents = parents;
+    }
+
+    /// Keep the root and its descendants in depth-first order, each below its nearest
+    /// ancestor in the list. Siblings stay sorted. Also returns the depth of each PID.
+    fn tree_order(&self, root: usize, indices: Vec<usize>) -> (Vec<usize>, HashMap<usize, usize>) {
+        let listed: HashSet<usize> = indices.iter().map(|&i| self.processes[i].pid).collect();
+        let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        for i in indices {
+            if let Some(parent) = self.listed_ancestor(self.processes[i].pid, root, &listed) {
+                children.entry(parent).or_default().push(i);
+            }
+        }
+
+        let (mut order, mut depths) = (Vec::new(), HashMap::new());
+        let top = children.remove(&None).unwrap_or_default();
+        let mut stack = top.into_iter().rev().map(|i| (i, 0)).collect_vec();
+        while let Some((i, depth)) = stack.pop() {
+            let pid = self.processes[i].pid;
+            order.push(i);
+            depths.insert(pid, depth);
+            if let Some(below) = children.remove(&Some(pid)) {
+                stack.extend(below.into_iter().rev().map(|i| (i, depth + 1)));
+            }
+        }
+        (order, depths)
+    }
+
+    /// The nearest ancestor of the process that is in the list, `Some(None)` for the root
+    /// and when there is none up to the root. `None` if the process is not in the tree.
+    fn listed_ancestor(
+        &self,
+        pid: usize,
+        root: usize,
+        listed: &HashSet<usize>,
+    ) -> Option<Option<usize>> {
+        if pid == root {
+            return Some(None);
+        }
+        let (mut current, mut nearest) = (pid, None);
+        // Bounded, a PID could be reused by an ancestor's ancestor in between lookups
+        for _ in 0..self.parents.len() {
+            let parent = (*self.parents.get(&current)?)?;
+            if nearest.is_none() && listed.contains(&parent) {
+                nearest = Some(parent);
+            }
+            if parent == root {
+                return Some(nearest);
+            }
+            current = parent;
+        }
+        None
+    }
+
+    /// The filter as it currently applies, including text that is still being edited.
+    fn current_filter(&self) -> Filter {
         let filter = match &self.state {
-            AppState::ShowList | AppState::ShowHelp => &self.filter,
             AppState::EditFilter(f) => f,
+            _ => &self.filter,
         };
+        Filter::new(filter, self.filter_mode)
+    }
 
-        self.processes.iter().filter(|p| show_in_filter(p, filter))
+    fn filtered_list(&self) -> impl Iterator<Item = &Process> {
+        let key = FilterKey {
+            text: match &self.state {
+                AppState::EditFilter(f) => f.clone(),
+                _ => self.filter.clone(),
+            },
+            mode: self.filter_mode,
+            sort: self.sort,
+            sort_reversed: self.sort_reversed,
+            public_only: self.public_only,
+            mine: self.mine,
+            tree: self.tree,
+        };
+
+        let mut cache = self.filter_cache.borrow_mut();
+        if cache.key.as_ref() != Some(&key) {
+            let filter = self.current_filter();
+            let indices = (0..self.processes.len())
+                .filter(|&i| filter.matches(&self.processes[i]))
+                .filter(|&i| {
+                    !self.public_only || self.processes[i].ports.iter().any(|s| !s.is_loopback())
+                })
+                .filter(|&i| !self.mine || self.processes[i].uid == procinfo::current_uid())
+                .filter(|&i| match &self.bind {
+                    Some(bind) => self.processes[i].ports.iter().any(|s| bind.matches(s)),
+                    None => true,
+                })
+                .filter(|&i| match &self.port_range {
+                    Some(range) => self.processes[i]
+                        .ports
+                        .iter()
+                        .any(|s| range.contains(&s.port)),
+                    None => true,
+                })
+                .collect_vec();
+            let matches: HashMap<usize, FuzzyMatch> = indices
+                .iter()
+                .filter_map(|&i| Some((i, filter.fuzzy_match(&self.processes[i].command)?)))
+                .collect();
+            // The best fuzzy matches come first, processes that only matched by port last
+            let indices = if matches.is_empty() {
+                indices
+                    .into_iter()
+                    .sorted_by(|&a, &b| {
+                        let ordering = self.sort.compare(&self.processes[a], &self.processes[b]);
+                        if self.sort_reversed {
+                            ordering.reverse()
+                        } else {
+                            ordering
+                        }
+                    })
+                    .collect()
+            } else {
+                indices
+                    .into_iter()
+                    .sorted_by_key(|i| {
+                        let score = matches.get(i).map(|m| m.score);
+                        (std::cmp::Reverse(score), self.processes[*i].pid)
+                    })
+                    .collect()
+            };
+            cache.matched = matches
+                .into_iter()
+                .map(|(i, m)| (self.processes[i].pid, m.positions))
+                .collect();
+            (cache.indices, cache.depths) = match self.tree {
+                Some(root) => self.tree_order(root, indices),
+                None => (indices, HashMap::new()),
+            };
+            cache.key = Some(key);
+        }
+
+        let indices = cache.indices.clone();
+        indices.into_iter().map(|i| &self.processes[i])
     }
 }
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        self.render_process_table(area, buf);
-        if matches!(self.state, AppState::ShowHelp) {
-            self.render_help(area, buf);
+        let log_height = match self.show_kill_log {
+            // At most a third of the screen
+            true => (self.kill_log.len().max(1) as u16 + 2).min(area.height / 3),
+            false => 0,
+        };
+        let [list_area, log_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(log_height)]).areas(area);
+        self.render_process_table(list_area, buf);
+        if self.show_kill_log {
+            self.render_kill_log(log_area, buf);
+        }
+        match &self.state {
+            AppState::ShowHelp(_) => self.render_help(area, buf),
+            AppState::PickSignal(_) => self.render_signal_picker(area, buf),
+            AppState::Detail(pid) => self.render_detail(*pid, area, buf),
+            AppState::ConfirmKill(targets, signal) => {
+                self.render_confirm_kill(targets, *signal, area, buf)
+            }
+            AppState::ConfirmSudo(targets, signal) => {
+                let title = Line::from(" Permission denied ".bold());
+                let pids = targets.iter().join(", ");
+                let items = vec![
+                    Line::from(vec![
+                        "Retry with ".into(),
+                        format!("sudo kill -{signal}").bold(),
+                        format!(" for {pids}?").into(),
+                    ]),
+                    "".into(),
+                    Line::from(vec![
+                        "<y>".bold(),
+                        " yes ".into(),
+                        "<n>".bold(),
+                        " no".into(),
+                    ]),
+                ];
+                render_popup(title, items, Style::new(), area, buf);
+            }
+            AppState::ConfirmRestart(pid) => {
+                let title = Line::from(" Restart ".bold());
+                let name = self.describe(Target::Process(*pid), false);
+                let items = vec![
+                    Line::from(vec![
+                        "Send ".into(),
+                        format!("SIG{}", self.kill_signal).bold(),
+                        format!(" to {name} and start it again?").into(),
+                    ]),
+                    "".into(),
+                    Line::from(vec![
+                        "<y>".bold(),
+                        " yes ".into(),
+                        "<n>".bold(),
+                        " no".into(),
+                    ]),
+                ];
+                render_popup(title, items, Style::new(), area, buf);
+            }
+            AppState::ConfirmQuit => {
+                let title = Line::from(" Quit ".bold());
+                let items = vec![
+                    "Quit portwitch?".into(),
+                    "".into(),
+                    Line::from(vec![
+                        "<y>".bold(),
+                        " yes ".into(),
+                        "<n>".bold(),
+                        " no".into(),
+                    ]),
+                ];
+                render_popup(title, items, Style::new(), area, buf);
+            }
+            _ => (),
+        }
+        if let Some(error) = &self.error {
+            self.render_error(error, area, buf);
         }
     }
 }
 
-fn show_in_filter(p: &Process, filter: &str) -> bool {
-    p.command.contains(filter)
-        || p.ports.iter().any(|port| port.contains(filter))
-        || p.pid.to_string().contains(filter)
+/// Render lines in a bordered box in the center of the area, sized to fit the content.
+/// Returns the area inside the border and padding.
+fn render_popup(title: Line, items: Vec<Line>, style: Style, area: Rect, buf: &mut Buffer) -> Rect {
+    render_scrolled_popup(title, items, 0, style, area, buf)
+}
+
+/// A popup that shows the lines from `offset` on, with a scrollbar if not all of them fit.
+fn render_scrolled_popup(
+    title: Line,
+    items: Vec<Line>,
+    offset: usize,
+    style: Style,
+    area: Rect,
+    buf: &mut Buffer,
+) -> Rect {
+    // Add border and padding to width and height
+    let height = (items.len() as u16 + 4).min(area.height);
+    let width = items
+        .iter()
+        .map(|line| line.width() as u16)
+        .max()
+        .unwrap_or(0)
+        + 6;
+    let width = width.max(title.width() as u16 + 2);
+    let area = area.centered(Constraint::Length(width), Constraint::Length(height));
+
+    let block = Block::bordered()
+        .title(title.centered())
+        .padding(Padding::proportional(1))
+        .border_set(border::ROUNDED)
+        .style(style);
+
+    let inner = block.inner(area);
+    let (total, fit) = (items.len(), usize::from(inner.height));
+    let list = List::new(items.into_iter().skip(offset)).block(block);
+    Widget::render(Clear, area, buf);
+    Widget::render(list, area, buf);
+    if total > fit {
+        let mut state = ScrollbarState::new(total - fit).position(offset);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None);
+        // On the right border, between the corners
+        let track = area.inner(Margin::new(0, 1));
+        StatefulWidget::render(scrollbar, track, buf, &mut state);
+    }
+    inner
+}
+
+/// Run the command with its output going to `$PAGER`, or `less` if that is not set.
+#[cfg(unix)]
+fn run_in_pager(mut command: process::Command) -> io::Result<()> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut child = command.stdout(process::Stdio::piped()).spawn()?;
+    let output = child.stdout.take().expect("stdout is piped");
+    // The pager may come with arguments, like `less -S`
+    let status = process::Command::new("sh")
+        .args(["-c", &pager])
+        .stdin(output)
+        .status();
+    child.wait()?;
+    status?;
+    Ok(())
+}
+
+/// Delete the last word and the whitespace after it, like ^W in a shell.
+fn delete_word(text: &mut String) {
+    let trimmed = text.trim_end();
+    let start = trimmed.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    text.truncate(start);
+}
+
+/// Join the items with commas, leaving out as many as needed to fit the width,
+/// like `80/tcp,443/tcp +3 more`.
+fn fit_list(items: &[String], width: usize) -> String {
+    let joined = items.join(",");
+    if joined.chars().count() <= width {
+        return joined;
+    }
+    (1..items.len())
+        .rev()
+        .map(|shown| {
+            let more = format!("+{} more", items.len() - shown);
+            format!("{} {more}", items[..shown].join(","))
+        })
+        .find(|text| text.chars().count() <= width)
+        .unwrap_or_else(|| format!("+{} more", items.len()))
+}
+
+/// Cut the text to the width, ending in `…` if anything was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
 }
 
 fn edit_filter_text(filter: &mut String, key: KeyCode) {
@@ -333,13 +2987,863 @@ fn edit_filter_text(filter: &mut String, key: KeyCode) {
     filter.push(c);
 }
 
-fn kill(pid: usize) {
-    Command::new("kill").arg(pid.to_string()).output().unwrap();
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portwitch::test_support::{FixedSource, process};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn app(processes: Vec<Process>) -> App {
+        let (_, receiver) = sync_channel(1);
+        let (intervals, _) = channel();
+        let mut app = App {
+            processes: Vec::new(),
+            exit: false,
+            table: TableState::default(),
+            filter: String::new(),
+            state: AppState::default(),
+            receiver,
+            intervals,
+            updater_crashes: 0,
+            warnings: Vec::new(),
+            error: None,
+            status: None,
+            sort: SortKey::default(),
+            sort_reversed: false,
+            filter_mode: FilterMode::default(),
+            kill_signal: Signal::default(),
+            confirm_kill: false,
+            confirm_quit: false,
+            update_interval: UPDATE_INTERVAL,
+            scan: Arc::default(),
+            source: Arc::new(FixedSource(Vec::new)),
+            paused: false,
+            public_only: false,
+            mine: false,
+            tree: None,
+            bind: None,
+            parents: HashMap::new(),
+            port_range: None,
+            watch_ports: Vec::new(),
+            stdin_ports: None,
+            table_layout: TableLayout::default(),
+            help_page: 1,
+            command_lines: HashMap::new(),
+            changes: HashMap::new(),
+            connection_counts: HashMap::new(),
+            port_connections: HashMap::new(),
+            theme: ThemeName::default().theme(),
+            marked: HashSet::new(),
+            usage: Vec::new(),
+            page_open_files: None,
+            shared_ports: HashSet::new(),
+            filter_history: History::default(),
+            filter_cache: RefCell::default(),
+            columns: Column::DEFAULT.to_vec(),
+            scanning: false,
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
+            count: None,
+            docker: false,
+            sudo: false,
+            sudo_retry: None,
+            restart: None,
+            last_message: None,
+            kill_log: Vec::new(),
+            show_kill_log: false,
+            service_names: true,
+            compact: false,
+            read_only: false,
+            dirty: true,
+            clear: false,
+            containers: None,
+        };
+        app.set_processes(Ok(processes.into()));
+        app
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_event(Event::Key(KeyEvent::from(code)));
+    }
+
+    fn type_filter(app: &mut App, text: &str) {
+        press(app, KeyCode::Char('/'));
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+        press(app, KeyCode::Enter);
+    }
+
+    fn selected_pid(app: &App) -> Option<usize> {
+        app.selected_process().map(|p| p.pid)
+    }
+
+    /// Let the highlights of new and gone processes run out.
+    fn expire_changes(app: &mut App) {
+        for (_, since) in app.changes.values_mut() {
+            *since -= CHANGE_DURATION;
+        }
+        app.expire_changes();
+    }
+
+    #[test]
+    fn shared_ports_are_found() {
+        let mut udp = process(3, "dns", 53);
+        udp.ports[0].protocol = Protocol::Udp;
+        let processes = vec![
+            process(1, "old", 8080),
+            process(2, "new", 8080),
+            process(3, "dns", 53),
+            udp,
+        ];
+        assert_eq!(
+            shared_ports(&processes),
+            HashSet::from([(Protocol::Tcp, 8080)])
+        );
+    }
+
+    #[test]
+    fn public_only() {
+        let mut local = process(1, "dev", 3000);
+        local.ports[0].address = "127.0.0.1".to_string();
+        let mut app = app(vec![local, process(2, "nginx", 80)]);
+        press(&mut app, KeyCode::Char('P'));
+        assert_eq!(app.filtered_list().map(|p| p.pid).collect_vec(), [2]);
+    }
+
+    #[test]
+    fn only_mine() {
+        let me = procinfo::current_uid().unwrap();
+        let mut mine = process(1, "dev", 3000);
+        mine.uid = Some(me);
+        let mut other = process(2, "nginx", 80);
+        other.uid = Some(me + 1);
+        let mut app = app(vec![mine, other]);
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.filtered_list().map(|p| p.pid).collect_vec(), [1]);
+    }
+
+    #[test]
+    fn process_tree() {
+        let mut app = app(vec![
+            process(10, "supervisor", 9000),
+            process(11, "web", 8080),
+            process(12, "worker", 8081),
+            process(13, "other", 8082),
+            process(14, "lonely", 8083),
+        ]);
+        // 12 is a grandchild through a process without ports
+        app.parents = HashMap::from([
+            (11, Some(10)),
+            (12, Some(20)),
+            (20, Some(11)),
+            (13, Some(1)),
+            (14, None),
+        ]);
+        app.table.select(Some(0));
+        press(&mut app, KeyCode::Char('t'));
+        assert_eq!(app.tree, Some(10));
+        assert_eq!(
+            app.filtered_list().map(|p| p.pid).collect_vec(),
+            [10, 11, 12]
+        );
+        assert_eq!(
+            app.cell(Column::Command, &app.processes[2], 20),
+            "  └ worker"
+        );
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.filtered_list().count(), 5);
+    }
+
+    #[test]
+    fn fuzzy_ranking() {
+        let mut app = app(vec![
+            process(1, "mongod", 27017),
+            process(2, "nginx", 80),
+            process(3, "ng-serve", 4200),
+            process(4, "other", 8080),
+        ]);
+        app.filter_mode = FilterMode::Fuzzy;
+        type_filter(&mut app, "ng");
+        assert_eq!(app.filtered_list().map(|p| p.pid).collect_vec(), [2, 3, 1]);
+        // Processes that only match by port come last
+        app.filter = "ng|8080".to_string();
+        assert_eq!(
+            app.filtered_list().map(|p| p.pid).collect_vec(),
+            [2, 3, 1, 4]
+        );
+        let line = app.command_cell(&app.processes[0], 20);
+        let matched = line
+            .spans
+            .iter()
+            .filter(|span| span.style == app.theme.matched)
+            .map(|span| span.content.as_ref())
+            .collect_vec();
+        assert_eq!(matched, ["ng"]);
+        // The … of a truncated command is never highlighted
+        let line = app.command_cell(&app.processes[2], 2);
+        assert_eq!(line.to_string(), "n…");
+        assert_eq!(line.spans[0].style, app.theme.matched);
+        assert_eq!(line.spans[1].style, Style::new());
+    }
+
+    #[test]
+    fn toggle_connections() {
+        let mut app = app(Vec::new());
+        app.source = Arc::new(FixedSource(|| {
+            let mut connection = process(2, "curl", 51234);
+            connection.ports[0].remote = Some("1.1.1.1:443".to_string());
+            vec![process(1, "nginx", 80), connection]
+        }));
+        press(&mut app, KeyCode::Char('c'));
+        assert!(app.scan_options().connections);
+        // Scanned again right away
+        assert_eq!(app.processes.len(), 2);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Showing listeners and established connections.")
+        );
+
+        let lines = render(&mut app, 100, 10);
+        assert!(lines[0].contains(" connections "), "{lines:#?}");
+        assert!(lines[1].contains("Remote"), "{lines:#?}");
+        assert!(lines.iter().any(|line| line.contains("1.1.1.1:443")));
+
+        press(&mut app, KeyCode::Char('c'));
+        assert!(!app.scan_options().connections);
+        let lines = render(&mut app, 100, 10);
+        assert!(!lines[0].contains(" connections "), "{lines:#?}");
+        assert!(!lines[1].contains("Remote"), "{lines:#?}");
+    }
 
-fn processes() -> Vec<Process> {
-    lsof::lsof()
-        .into_iter()
-        .filter(|p| !p.ports.is_empty())
-        .collect()
+    #[test]
+    fn connections_column() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "sshd", 22)]);
+        let lines = render(&mut app, 100, 10);
+        assert!(!lines[1].contains("Conns"), "{lines:#?}");
+
+        app.scan.lock().unwrap().count_connections = true;
+        app.port_connections = HashMap::from([((Protocol::Tcp, 80), 12), ((Protocol::Tcp, 22), 0)]);
+        let lines = render(&mut app, 100, 10);
+        assert!(lines[1].contains("Conns"), "{lines:#?}");
+        assert_eq!(app.cell(Column::Conns, &app.processes[0], 5), "   12");
+        assert_eq!(app.cell(Column::Conns, &app.processes[1], 5), "    0");
+    }
+
+    #[test]
+    fn restart_is_confirmed() {
+        // This test itself, which is never killed
+        let pid = process::id() as usize;
+        let mut app = app(vec![process(pid, "portwitch", 80)]);
+        app.confirm_kill = true;
+        app.table.select(Some(0));
+        press(&mut app, KeyCode::Char('R'));
+        if procinfo::arguments(pid).is_none() {
+            // Not known on this platform
+            assert!(matches!(app.state, AppState::ShowList));
+            return;
+        }
+        assert!(matches!(app.state, AppState::ConfirmRestart(p) if p == pid));
+        press(&mut app, KeyCode::Char('n'));
+        assert!(matches!(app.state, AppState::ShowList));
+        assert!(app.restart.is_none());
+    }
+
+    #[test]
+    fn restart_times_out() {
+        let pid = process::id() as usize;
+        let mut app = app(vec![process(pid, "portwitch", 80)]);
+        app.restart = Some(PendingRestart {
+            pid,
+            name: "portwitch".to_string(),
+            args: vec!["false".into()],
+            dir: None,
+            killed: Instant::now(),
+        });
+        app.continue_restart();
+        assert!(app.restart.is_some());
+
+        app.restart.as_mut().unwrap().killed -= RESTART_TIMEOUT;
+        app.continue_restart();
+        assert!(app.restart.is_none());
+        let (message, _) = app.last_message.as_ref().unwrap();
+        assert_eq!(message, "portwitch did not exit within 3s, not restarted.");
+    }
+
+    #[test]
+    fn read_only() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        app.read_only = true;
+        app.table.select(Some(0));
+        for key in ['x', 'X', 'K', ' '] {
+            press(&mut app, KeyCode::Char(key));
+            assert!(matches!(app.state, AppState::ShowList), "{key}");
+            assert_eq!(app.status.as_deref(), Some(READ_ONLY));
+        }
+        assert!(app.marked.is_empty());
+        app.run_command("kill 80");
+        assert!(app.kill_log.is_empty());
+        assert_eq!(app.status.as_deref(), Some(READ_ONLY));
+
+        let lines = render(&mut app, 80, 5).join("\n");
+        assert!(lines.contains("read-only"));
+        assert!(!lines.contains("to kill"));
+    }
+
+    #[test]
+    fn connection_counts() {
+        let with_connections = |count: usize| {
+            let mut server = process(1, "nginx", 80);
+            for i in 0..count {
+                let mut connection = server.ports[0].clone();
+                connection.remote = Some(format!("10.0.0.{i}:5000"));
+                server.ports.push(connection);
+            }
+            vec![server]
+        };
+        let mut app = app(with_connections(0));
+        app.scan.lock().unwrap().connections = true;
+        for count in [0, 2, 5].into_iter().cycle().take(CONNECTION_HISTORY + 2) {
+            app.set_processes(Ok(with_connections(count).into()));
+        }
+        let counts = &app.connection_counts[&1];
+        assert_eq!(counts.len(), CONNECTION_HISTORY);
+        assert_eq!(counts.iter().take(3).copied().collect_vec(), [5, 0, 2]);
+
+        app.state = AppState::Detail(1);
+        let lines = render(&mut app, 100, 30);
+        let line = lines.iter().find(|l| l.contains("Connections")).unwrap();
+        assert!(line.contains("Connections 2"));
+        assert!(line.contains('█'));
+
+        app.scan.lock().unwrap().connections = false;
+        app.set_processes(Ok(with_connections(0).into()));
+        assert!(app.connection_counts.is_empty());
+    }
+
+    #[test]
+    fn interval_steps() {
+        let ms = Duration::from_millis;
+        assert_eq!(next_interval(ms(500), true), Some(ms(1000)));
+        assert_eq!(next_interval(ms(1500), false), Some(ms(1000)));
+        assert_eq!(next_interval(ms(100), false), None);
+        assert_eq!(next_interval(ms(10_000), true), None);
+        assert_eq!(next_interval(ms(60_000), false), Some(ms(10_000)));
+
+        assert_eq!(format_interval(ms(500)), "500ms");
+        assert_eq!(format_interval(ms(2000)), "2s");
+        assert_eq!(format_interval(ms(1500)), "1.5s");
+    }
+
+    #[test]
+    fn bind_address() {
+        let mut app = app(vec![
+            process(1, "sshd", 22),
+            process(2, "node", 3000),
+            process(3, "vite", 5173),
+        ]);
+        app.processes[1].ports[0].address = "192.168.1.10".to_string();
+        app.processes[2].ports[0].address = "127.0.0.1".to_string();
+        let pids = |app: &mut App, bind| {
+            app.bind = Some(bind);
+            app.filter_cache = RefCell::default();
+            app.filtered_list().map(|p| p.pid).collect_vec()
+        };
+        // The wildcard listener is reachable on every address
+        assert_eq!(pids(&mut app, Bind::address("192.168.1.10")), [1, 2]);
+        assert_eq!(pids(&mut app, Bind::address("0.0.0.0")), [1]);
+        assert_eq!(pids(&mut app, Bind::address("[::]")), [1]);
+    }
+
+    #[test]
+    fn port_range() {
+        let mut app = app(vec![
+            process(1, "sshd", 22),
+            process(2, "node", 3000),
+            process(3, "vite", 5173),
+        ]);
+        app.port_range = Some(1024..=u16::MAX);
+        app.filter_cache = RefCell::default();
+        assert_eq!(app.filtered_list().map(|p| p.pid).collect_vec(), [2, 3]);
+
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(
+            app.filtered_list().map(|p| p.pid).collect_vec(),
+            [] as [usize; 0]
+        );
+    }
+
+    #[test]
+    fn delete_last_word() {
+        let delete = |text: &str| {
+            let mut text = text.to_string();
+            delete_word(&mut text);
+            text
+        };
+        assert_eq!(delete("node >1024"), "node ");
+        assert_eq!(delete("node "), "");
+        assert_eq!(delete("node"), "");
+        assert_eq!(delete(""), "");
+    }
+
+    #[test]
+    fn restored_selection_prefers_same_pid() {
+        assert_eq!(restored_selection(Some((3, 0)), &[1, 2, 3]), Some(2));
+        // Gone, keep the position
+        assert_eq!(restored_selection(Some((9, 1)), &[1, 2, 3]), Some(1));
+        // Gone and the list got shorter, select the nearest row
+        assert_eq!(restored_selection(Some((9, 5)), &[1, 2, 3]), Some(2));
+        assert_eq!(restored_selection(None, &[1, 2, 3]), Some(0));
+        assert_eq!(restored_selection(Some((1, 0)), &[]), None);
+    }
+
+    #[test]
+    fn first_row_is_selected() {
+        let app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        assert_eq!(selected_pid(&app), Some(1));
+    }
+
+    #[test]
+    fn selection_follows_pid_when_filtering() {
+        let mut app = app(vec![
+            process(1, "nginx", 80),
+            process(2, "node", 3000),
+            process(3, "node", 3001),
+        ]);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_pid(&app), Some(3));
+
+        type_filter(&mut app, "node");
+        assert_eq!(app.table.selected(), Some(1));
+        assert_eq!(selected_pid(&app), Some(3));
+    }
+
+    #[test]
+    fn selection_comes_back_after_empty_filter() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.filtered_list().count(), 0);
+        assert_eq!(selected_pid(&app), None);
+
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(selected_pid(&app), Some(1));
+    }
+
+    #[test]
+    fn sorting_keeps_selected_pid() {
+        let mut app = app(vec![
+            process(1, "zsh", 22),
+            process(2, "apache", 8080),
+            process(3, "mysql", 3306),
+        ]);
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_pid(&app), Some(2));
+
+        // Sort by command
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.table.selected(), Some(0));
+        assert_eq!(selected_pid(&app), Some(2));
+
+        // Sort by port
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.table.selected(), Some(2));
+        assert_eq!(selected_pid(&app), Some(2));
+
+        press(&mut app, KeyCode::Char('S'));
+        assert_eq!(app.table.selected(), Some(0));
+        assert_eq!(selected_pid(&app), Some(2));
+    }
+
+    #[test]
+    fn sorting_keeps_screen_row() {
+        let mut app = app((1..=100)
+            .map(|pid| process(pid, "node", 4000 - pid as u16))
+            .collect());
+        // Row 50 is shown 10 rows from the top
+        app.table.select(Some(50));
+        *app.table.offset_mut() = 40;
+
+        press(&mut app, KeyCode::Char('S'));
+        assert_eq!(app.table.selected(), Some(49));
+        assert_eq!(app.table.offset(), 39);
+        assert_eq!(selected_pid(&app), Some(51));
+    }
+
+    #[test]
+    fn exited_process_selects_nearest_row() {
+        let mut app = app(vec![
+            process(1, "nginx", 80),
+            process(2, "node", 3000),
+            process(3, "node", 3001),
+        ]);
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_pid(&app), Some(2));
+
+        app.set_processes(Ok(
+            vec![process(1, "nginx", 80), process(3, "node", 3001)].into()
+        ));
+        expire_changes(&mut app);
+        assert_eq!(selected_pid(&app), Some(3));
+
+        app.set_processes(Ok(vec![process(1, "nginx", 80)].into()));
+        expire_changes(&mut app);
+        assert_eq!(selected_pid(&app), Some(1));
+    }
+
+    #[test]
+    fn watched_ports_from_stdin() {
+        assert_eq!(parse_watched_port(" 8080 "), Some(Ok(8080)));
+        assert_eq!(parse_watched_port(":3000"), Some(Ok(3000)));
+        assert_eq!(parse_watched_port(""), None);
+        assert_eq!(parse_watched_port("http"), Some(Err("http".to_string())));
+        assert_eq!(parse_watched_port("70000"), Some(Err("70000".to_string())));
+    }
+
+    #[test]
+    fn updater_crashes() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        app.updater_crashes = UPDATER_RESTARTS;
+        // The receiver of the test app has no sender, like after a crash
+        app.refresh_processes();
+        assert!(app.updates_stopped());
+        assert!(app.clear);
+        assert_eq!(app.processes.len(), 1);
+    }
+
+    #[test]
+    fn updater_recovers() {
+        let mut app = app(Vec::new());
+        (app.receiver, app.intervals) = spawn_process_updater(
+            Arc::new(FixedSource(|| panic!("broken scan"))),
+            UPDATE_INTERVAL,
+            app.scan.clone(),
+        );
+        // The restarted updater scans with the source of the app
+        app.source = Arc::new(FixedSource(|| vec![process(1, "nginx", 80)]));
+        let started = Instant::now();
+        while app.processes.is_empty() && started.elapsed() < Duration::from_secs(5) {
+            app.refresh_processes();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(app.updater_crashes, 1);
+        assert!(app.clear);
+        assert!(!app.updates_stopped());
+        assert_eq!(app.processes.len(), 1);
+    }
+
+    #[test]
+    fn interval_changes_the_running_updater() {
+        let mut app = app(Vec::new());
+        app.update_interval = Duration::from_secs(60);
+        (app.receiver, app.intervals) = spawn_process_updater(
+            Arc::new(FixedSource(|| vec![process(1, "nginx", 80)])),
+            app.update_interval,
+            app.scan.clone(),
+        );
+        assert!(app.receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        // The updater that waits for a minute scans again soon after
+        while next_interval(app.update_interval, false).is_some() {
+            app.change_interval(false);
+        }
+        assert_eq!(app.update_interval, INTERVALS[0]);
+        assert!(app.receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn changes_are_highlighted() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        expire_changes(&mut app);
+        assert!(app.changes.is_empty());
+
+        app.set_processes(Ok(
+            vec![process(1, "nginx", 80), process(3, "vite", 5173)].into()
+        ));
+        assert!(matches!(app.changes[&3], (Change::Appeared, _)));
+        assert!(matches!(app.changes[&2], (Change::Gone, _)));
+        // Gone, but still shown for the highlight
+        assert_eq!(app.filtered_list().count(), 3);
+
+        expire_changes(&mut app);
+        assert!(app.changes.is_empty());
+        assert_eq!(app.filtered_list().map(|p| p.pid).collect_vec(), [1, 3]);
+    }
+
+    #[test]
+    fn long_lists_are_cut() {
+        let ports = ["80/tcp", "443/tcp", "8080/tcp"].map(String::from);
+        assert_eq!(fit_list(&ports, 23), "80/tcp,443/tcp,8080/tcp");
+        assert_eq!(fit_list(&ports, 22), "80/tcp,443/tcp +1 more");
+        assert_eq!(fit_list(&ports, 15), "80/tcp +2 more");
+        assert_eq!(fit_list(&ports, 5), "+3 more");
+        assert_eq!(fit_list(&[], 5), "");
+    }
+
+    #[test]
+    fn unchanged_scan_is_not_drawn() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        app.dirty = false;
+        app.set_processes(Ok(vec![process(1, "nginx", 80)].into()));
+        assert!(!app.dirty);
+
+        app.set_processes(Ok(vec![process(2, "node", 3000)].into()));
+        assert!(app.dirty);
+    }
+
+    #[test]
+    fn quit_on_error() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        app.set_processes(Err(io::Error::other("lsof failed")));
+        assert!(app.error.is_some());
+        press(&mut app, KeyCode::Char('x'));
+        assert!(!app.exit);
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.exit);
+    }
+
+    #[test]
+    fn mouse_moves_are_not_drawn() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        let mouse = |kind| {
+            Event::Mouse(MouseEvent {
+                kind,
+                column: 0,
+                row: 0,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        app.dirty = false;
+        app.handle_event(mouse(MouseEventKind::Moved));
+        assert!(!app.dirty);
+
+        app.handle_event(mouse(MouseEventKind::ScrollDown));
+        assert!(app.dirty);
+        app.dirty = false;
+        app.handle_event(Event::Resize(80, 24));
+        assert!(app.dirty);
+        app.dirty = false;
+        press(&mut app, KeyCode::Down);
+        assert!(app.dirty);
+    }
+
+    #[test]
+    fn kill_command() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        let run = |app: &mut App, command: &str| {
+            press(app, KeyCode::Char(':'));
+            for c in command.chars() {
+                press(app, KeyCode::Char(c));
+            }
+            press(app, KeyCode::Enter);
+        };
+
+        run(&mut app, "kill 3000");
+        let AppState::ConfirmKill(targets, _) = &app.state else {
+            panic!("expected a confirmation, got {:?}", app.state);
+        };
+        assert_eq!(targets, &[Target::Process(2)]);
+        press(&mut app, KeyCode::Char('n'));
+
+        // Not a port, so a PID
+        run(&mut app, "kill 1");
+        assert!(matches!(&app.state, AppState::ConfirmKill(t, _) if t == &[Target::Process(1)]));
+        press(&mut app, KeyCode::Char('n'));
+
+        run(&mut app, "stop 80");
+        assert!(matches!(app.state, AppState::ShowList));
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Error: unknown command `stop`.")
+        );
+    }
+
+    #[test]
+    fn row_numbers() {
+        let processes = (1..=12).map(|pid| process(pid, "node", 3000 + pid as u16));
+        let mut app = app(processes.collect());
+
+        press(&mut app, KeyCode::Char('1'));
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(selected_pid(&app), Some(1));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(selected_pid(&app), Some(11));
+        assert!(matches!(app.state, AppState::ShowList));
+
+        press(&mut app, KeyCode::Char('3'));
+        press(&mut app, KeyCode::Char('k'));
+        assert_eq!(selected_pid(&app), Some(8));
+        press(&mut app, KeyCode::Char('2'));
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_pid(&app), Some(10));
+
+        // Just Esc and a plain `j` after a cancelled count
+        press(&mut app, KeyCode::Char('5'));
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(selected_pid(&app), Some(11));
+
+        // Digits in the filter are text
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('3'));
+        assert!(app.count.is_none());
+    }
+
+    #[test]
+    fn piped_filter() {
+        assert_eq!(stdin_filter("8080\n3000\n"), "8080|3000");
+        assert_eq!(stdin_filter("node :80 \n\n"), "node|:80");
+        assert_eq!(stdin_filter(""), "");
+    }
+
+    /// Draw the app like a terminal of this size would show it.
+    fn render(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn list_is_rendered() {
+        let mut app = app(vec![process(1, "nginx", 80), process(4821, "node", 3000)]);
+        let lines = render(&mut app, 80, 10);
+        assert!(lines[0].contains("portwitch"), "{lines:#?}");
+        assert!(lines[0].contains(" 1/2 "), "{lines:#?}");
+        for header in ["PID", "Command", "Address", "Ports"] {
+            assert!(lines[1].contains(header), "{header}: {lines:#?}");
+        }
+        let words = |line: &str| line.split_whitespace().join(" ");
+        assert_eq!(words(&lines[2]), "> 1 nginx * 80/tcp (http)");
+        assert_eq!(words(&lines[3]), "4821 node * 3000/tcp");
+        assert!(lines[9].contains("2 TCP, 0 UDP, 0 IPv6"), "{lines:#?}");
+    }
+
+    #[test]
+    fn long_commands_are_cut() {
+        assert_eq!(truncate("node", 4), "node");
+        assert_eq!(truncate("java-classpath", 5), "java…");
+        assert_eq!(truncate("java", 0), "");
+
+        let command = "java-org.apache.catalina.startup.Bootstrap";
+        let mut app = app(vec![process(1, command, 8080)]);
+        let lines = render(&mut app, 60, 5);
+        let row = lines[2].split_whitespace().collect_vec();
+        assert_eq!(row[..2], [">", "1"], "{lines:#?}");
+        assert!(
+            row[2].starts_with("java-org") && row[2].ends_with('…'),
+            "{lines:#?}"
+        );
+        assert!(lines[2].contains("8080/tcp"), "{lines:#?}");
+    }
+
+    #[test]
+    fn plain_theme_has_no_colors() {
+        let mut app = app(vec![process(1, "nginx", 80), process(2, "node", 3000)]);
+        app.theme = ThemeName::Plain.theme();
+        app.watch_ports = vec![3000];
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        for cell in &buffer.content {
+            assert_eq!((cell.fg, cell.bg), (Color::Reset, Color::Reset), "{cell:?}");
+        }
+        // The selection stands out without colors
+        assert!(buffer[(5, 2)].modifier.contains(Modifier::REVERSED));
+        assert!(!buffer[(5, 3)].modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn compact_list_is_rendered() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        app.compact = true;
+        let lines = render(&mut app, 60, 3);
+        assert_eq!(
+            lines[0].split_whitespace().join(" "),
+            "> 1 nginx 80/tcp (http)"
+        );
+        assert!(
+            lines[1..].iter().all(|line| line.trim().is_empty()),
+            "{lines:#?}"
+        );
+    }
+
+    #[test]
+    fn empty_list_is_explained() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        type_filter(&mut app, "redis");
+        let lines = render(&mut app, 60, 10);
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.contains("No processes match 'redis'")),
+            "{lines:#?}"
+        );
+    }
+
+    #[test]
+    fn help_scrolls() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        press(&mut app, KeyCode::Char('?'));
+        let lines = render(&mut app, 80, 24);
+        let text = lines.join("\n");
+        // The whole popup is on the screen, with a scrollbar for the rest
+        assert!(lines.iter().any(|line| line.contains(" Help ")), "{text}");
+        assert!(lines.iter().any(|line| line.contains('╰')), "{text}");
+        assert!(text.contains("Clear marks"), "{text}");
+        assert!(!text.contains("$ portwitch"), "{text}");
+        assert!(text.contains('█'), "{text}");
+
+        press(&mut app, KeyCode::Char('j'));
+        let text = render(&mut app, 80, 24).join("\n");
+        assert!(!text.contains("Clear marks"), "{text}");
+        assert!(matches!(app.state, AppState::ShowHelp(1)));
+
+        press(&mut app, KeyCode::End);
+        let text = render(&mut app, 80, 24).join("\n");
+        assert!(text.contains("$ portwitch 8080"), "{text}");
+        // Going back up starts from the end, not from far below it
+        let AppState::ShowHelp(end) = app.state else {
+            panic!("{:?}", app.state);
+        };
+        press(&mut app, KeyCode::PageUp);
+        assert!(matches!(app.state, AppState::ShowHelp(offset) if offset == end - app.help_page));
+        press(&mut app, KeyCode::Char('g'));
+        let text = render(&mut app, 80, 24).join("\n");
+        assert!(text.contains("Clear marks"), "{text}");
+    }
+
+    #[test]
+    fn help_is_centered() {
+        let mut app = app(vec![process(1, "nginx", 80)]);
+        press(&mut app, KeyCode::Char('?'));
+        let (width, height) = (100, 70);
+        let lines = render(&mut app, width, height);
+
+        let top = lines
+            .iter()
+            .position(|line| line.contains(" Help "))
+            .unwrap();
+        let bottom = lines.iter().rposition(|line| line.contains('╰')).unwrap();
+        assert!(
+            top.abs_diff(height as usize - 1 - bottom) <= 1,
+            "{lines:#?}"
+        );
+
+        let row: Vec<char> = lines[top].chars().collect();
+        let left = row.iter().position(|&c| c == '╭').unwrap();
+        let right = row.iter().position(|&c| c == '╮').unwrap();
+        assert!(left.abs_diff(width as usize - 1 - right) <= 1, "{lines:#?}");
+        // Every help line is inside the border
+        for line in &lines[top + 1..bottom] {
+            let row: Vec<char> = line.chars().collect();
+            assert_eq!((row[left], row[right]), ('│', '│'), "{lines:#?}");
+        }
+    }
 }
@@ -0,0 +1,181 @@
+use crate::source::{PortSource, Process, Protocol, Scan, ScanOptions, Socket};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+/// Finds listening processes on Windows with `netstat` and `tasklist`.
+#[derive(Debug)]
+pub struct Netstat;
+
+impl PortSource for Netstat {
+    fn scan(&self, options: ScanOptions) -> io::Result<Scan> {
+        let netstat = run("netstat", &["-ano"])?;
+        let tasklist = run("tasklist", &["/fo", "csv", "/nh"])?;
+        Ok(processes(&netstat, &tasklist, options).into())
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run {program}: {e}")))?;
+    // Otherwise a failing netstat would look like nothing is listening
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "{program} failed with {}: {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The processes with sockets in the netstat output, named after their image in the tasklist output.
+fn processes(netstat: &str, tasklist: &str, options: ScanOptions) -> Vec<Process> {
+    let names = parse_tasklist_output(tasklist);
+    parse_netstat_output(netstat, options)
+        .into_iter()
+        .into_group_map()
+        .into_iter()
+        .map(|(pid, ports)| Process {
+            pid,
+            pgid: None,
+            ppid: None,
+            uid: None,
+            started: None,
+            command: names.get(&pid).cloned().unwrap_or_else(|| "?".to_string()),
+            ports: ports.into_iter().unique().collect(),
+        })
+        .collect()
+}
+
+/// Parse lines like `TCP    0.0.0.0:135    0.0.0.0:0    LISTENING    1234`.
+/// UDP lines have no state column.
+fn parse_netstat_output(out: &str, options: ScanOptions) -> Vec<(usize, Socket)> {
+    out.lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let (protocol, local, remote, pid) = match columns.as_slice() {
+                ["TCP", local, _, "LISTENING", pid] => (Protocol::Tcp, local, None, pid),
+                ["TCP", local, remote, "ESTABLISHED", pid] if options.reads_connections() => {
+                    (Protocol::Tcp, local, Some(remote.to_string()), pid)
+                }
+                ["UDP", local, _, pid] => (Protocol::Udp, local, None, pid),
+                _ => return None,
+            };
+            if !options.wants(protocol) {
+                return None;
+            }
+            let mut socket = Socket::parse_local(protocol, local)?;
+            socket.remote = remote;
+            Some((pid.parse().ok()?, socket))
+        })
+        .collect()
+}
+
+/// Parse lines like `"node.exe","1234","Console","1","51,220 K"` into PID → image name.
+fn parse_tasklist_output(out: &str) -> HashMap<usize, String> {
+    out.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(|f| f.trim().trim_matches('"'));
+            let name = fields.next()?;
+            let pid = fields.next()?.parse().ok()?;
+            Some((pid, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Family;
+
+    const NETSTAT: &str = include_str!("../tests/fixtures/netstat-windows.txt");
+    const TASKLIST: &str = include_str!("../tests/fixtures/tasklist.txt");
+
+    fn sockets(options: ScanOptions) -> Vec<(usize, Protocol, Family, String, u16)> {
+        parse_netstat_output(NETSTAT, options)
+            .into_iter()
+            .map(|(pid, s)| (pid, s.protocol, s.family, s.address, s.port))
+            .collect()
+    }
+
+    #[test]
+    fn listeners() {
+        let tcp = |pid, family, address: &str, port| {
+            (pid, Protocol::Tcp, family, address.to_string(), port)
+        };
+        let udp = |pid, family, address: &str, port| {
+            (pid, Protocol::Udp, family, address.to_string(), port)
+        };
+        assert_eq!(
+            sockets(ScanOptions::default()),
+            [
+                tcp(1012, Family::V4, "*", 135),
+                tcp(4821, Family::V4, "*", 3000),
+                tcp(1012, Family::V6, "*", 135),
+                tcp(2210, Family::V6, "::1", 5432),
+                udp(2596, Family::V4, "*", 5353),
+                udp(2596, Family::V6, "*", 5353),
+                // Without the scope of the link-local address
+                udp(3120, Family::V6, "fe80::1", 1900),
+            ]
+        );
+
+        let udp_only = ScanOptions {
+            protocol: Some(Protocol::Udp),
+            ..ScanOptions::default()
+        };
+        assert_eq!(sockets(udp_only).len(), 3);
+    }
+
+    #[test]
+    fn connections() {
+        let options = ScanOptions {
+            connections: true,
+            ..ScanOptions::default()
+        };
+        let connections = parse_netstat_output(NETSTAT, options)
+            .into_iter()
+            .filter_map(|(pid, s)| Some((pid, s.port, s.remote?)))
+            .collect::<Vec<_>>();
+        // Not the ones in TIME_WAIT, those have no process anymore
+        assert_eq!(
+            connections,
+            [
+                (4821, 3000, "127.0.0.1:51234".to_string()),
+                (7340, 51234, "127.0.0.1:3000".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn names() {
+        let names = parse_tasklist_output(TASKLIST);
+        assert_eq!(names[&0], "System Idle Process");
+        // The memory column has a comma too
+        assert_eq!(names[&4821], "node.exe");
+        assert_eq!(names.len(), 6);
+
+        let mut processes = processes(NETSTAT, TASKLIST, ScanOptions::default());
+        processes.sort_by_key(|p| p.pid);
+        let commands = processes
+            .iter()
+            .map(|p| (p.pid, p.command.as_str(), p.ports.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                (1012, "svchost.exe", 2),
+                (2210, "postgres.exe", 1),
+                (2596, "svchost.exe", 2),
+                // Gone before tasklist ran
+                (3120, "?", 1),
+                (4821, "node.exe", 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! Details about a process that the port sources don't report.
+
+#[cfg(target_os = "linux")]
+use itertools::Itertools;
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The full command line with arguments, e.g. `node /srv/api/server.js --port 3000`.
+pub fn command_line(pid: usize) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    if let Some(cmdline) = read_proc_cmdline(pid) {
+        return Some(cmdline);
+    }
+    ps_command(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn read_proc_cmdline(pid: usize) -> Option<String> {
+    let args = read_proc_arguments(pid)?;
+    Some(args.iter().map(|arg| arg.to_string_lossy()).join(" "))
+}
+
+/// Arguments in `/proc/<pid>/cmdline` are separated by NUL bytes.
+#[cfg(target_os = "linux")]
+fn read_proc_arguments(pid: usize) -> Option<Vec<OsString>> {
+    use std::os::unix::ffi::OsStrExt;
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    // Kernel threads have an empty command line
+    let args = cmdline
+        .split(|&b| b == b'\0')
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| OsStr::from_bytes(arg).to_os_string())
+        .collect::<Vec<_>>();
+    (!args.is_empty()).then_some(args)
+}
+
+/// The arguments the process was started with, like `["node", "server.js"]`, to start it again.
+/// Only Linux has them one by one. Splitting the command line from `ps` would break
+/// arguments with spaces, so there are none elsewhere.
+pub fn arguments(pid: usize) -> Option<Vec<OsString>> {
+    #[cfg(target_os = "linux")]
+    return read_proc_arguments(pid);
+    #[cfg(not(target_os = "linux"))]
+    return {
+        let _ = pid;
+        None
+    };
+}
+
+/// The directory the process runs in.
+pub fn working_dir(pid: usize) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    return std::fs::read_link(format!("/proc/{pid}/cwd")).ok();
+    #[cfg(not(target_os = "linux"))]
+    return {
+        let _ = pid;
+        None
+    };
+}
+
+#[cfg(unix)]
+fn ps_command(pid: usize) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "command=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !command.is_empty()).then_some(command)
+}
+
+/// Windows has no `ps`, ask WMI through PowerShell.
+#[cfg(windows)]
+fn ps_command(pid: usize) -> Option<String> {
+    let query = format!("(Get-CimInstance Win32_Process -Filter 'ProcessId={pid}').CommandLine");
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &query])
+        .output()
+        .ok()?;
+    let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !command.is_empty()).then_some(command)
+}
+
+/// When the process was started.
+pub fn start_time(pid: usize) -> Option<SystemTime> {
+    #[cfg(target_os = "linux")]
+    if let Some(started) = read_proc_start_time(pid) {
+        return Some(started);
+    }
+    #[cfg(unix)]
+    return ps_elapsed(pid).and_then(|elapsed| SystemTime::now().checked_sub(elapsed));
+    #[cfg(windows)]
+    return {
+        let _ = pid;
+        None
+    };
+}
+
+/// The PID of the parent process.
+pub fn parent_pid(pid: usize) -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    if let Some(ppid) = read_proc_parent(pid) {
+        return Some(ppid);
+    }
+    #[cfg(unix)]
+    return Command::new("ps")
+        .args(["-o", "ppid=", "-p", &pid.to_string()])
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok());
+    #[cfg(windows)]
+    return {
+        let _ = pid;
+        None
+    };
+}
+
+/// The parent is the second field after the command in `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn read_proc_parent(pid: usize) -> Option<usize> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// USER_HZ, the unit of times that the kernel reports to user space.
+#[cfg(target_os = "linux")]
+const TICKS_PER_SECOND: u64 = 100;
+
+/// The start time in `/proc/<pid>/stat` is in clock ticks after boot,
+/// and the boot time is in `/proc/stat`.
+#[cfg(target_os = "linux")]
+fn read_proc_start_time(pid: usize) -> Option<SystemTime> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command in parentheses may contain spaces, starttime is the 20th field after it.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let ticks: u64 = fields.split_whitespace().nth(19)?.parse().ok()?;
+
+    let boot = std::fs::read_to_string("/proc/stat").ok()?;
+    let boot: u64 = boot
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+
+    let since_boot = Duration::from_millis(ticks * 1000 / TICKS_PER_SECOND);
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(boot) + since_boot)
+}
+
+/// CPU and memory use of a process at one point in time.
+#[derive(Copy, Clone, Debug)]
+pub struct Sample {
+    taken: Instant,
+    /// CPU time used since the process started.
+    cpu_time: Duration,
+    /// Resident memory in bytes.
+    pub memory: u64,
+}
+
+impl Sample {
+    /// CPU use between an earlier sample and this one. A busy core is 100%.
+    pub fn cpu_percent(&self, earlier: &Sample) -> f64 {
+        let wall = self.taken.duration_since(earlier.taken).as_secs_f64();
+        let cpu = self.cpu_time.saturating_sub(earlier.cpu_time).as_secs_f64();
+        if wall > 0.0 { cpu / wall * 100.0 } else { 0.0 }
+    }
+
+    /// When the sample was taken.
+    pub fn taken(&self) -> Instant {
+        self.taken
+    }
+}
+
+/// Sample the CPU and memory use. `None` if the process is gone.
+pub fn sample(pid: usize) -> Option<Sample> {
+    #[cfg(target_os = "linux")]
+    if let Some(sample) = read_proc_sample(pid) {
+        return Some(sample);
+    }
+    #[cfg(unix)]
+    return ps_sample(pid);
+    #[cfg(windows)]
+    return {
+        let _ = pid;
+        None
+    };
+}
+
+/// CPU time is in `/proc/<pid>/stat` as clock ticks in user and kernel mode,
+/// memory in `/proc/<pid>/status` like `VmRSS:    1234 kB`.
+#[cfg(target_os = "linux")]
+fn read_proc_sample(pid: usize) -> Option<Sample> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    // Kernel threads have no memory of their own and no VmRSS line
+    let rss_kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rss| rss.trim().strip_suffix("kB")?.trim().parse().ok())
+        .unwrap_or(0);
+
+    Some(Sample {
+        taken: Instant::now(),
+        cpu_time: Duration::from_millis((utime + stime) * 1000 / TICKS_PER_SECOND),
+        memory: rss_kb * 1024,
+    })
+}
+
+/// `ps` prints the CPU time like the elapsed time, with hundredths of seconds on macOS,
+/// and the memory in KiB.
+#[cfg(unix)]
+fn ps_sample(pid: usize) -> Option<Sample> {
+    let output = Command::new("ps")
+        .args(["-o", "time=,rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let (time, rss) = output.trim().split_once(char::is_whitespace)?;
+    Some(Sample {
+        taken: Instant::now(),
+        cpu_time: parse_elapsed(time)?,
+        memory: rss.trim().parse::<u64>().ok()? * 1024,
+    })
+}
+
+/// `ps` prints the elapsed time as `[[dd-]hh:]mm:ss`.
+#[cfg(unix)]
+fn ps_elapsed(pid: usize) -> Option<Duration> {
+    let output = Command::new("ps")
+        .args(["-o", "etime=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    parse_elapsed(String::from_utf8_lossy(&output.stdout).trim())
+}
+
+#[cfg(unix)]
+fn parse_elapsed(etime: &str) -> Option<Duration> {
+    let (days, time) = match etime.split_once('-') {
+        Some((days, time)) => (days.parse().ok()?, time),
+        None => (0, etime),
+    };
+    // The seconds may have a fraction, like `0:01.23`
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, format!("0.{fraction}").parse().ok()?),
+        None => (time, 0.0),
+    };
+    let mut seconds = 0;
+    for part in time.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    let seconds = Duration::from_secs(days * 24 * 60 * 60 + seconds);
+    Some(seconds + Duration::from_secs_f64(fraction))
+}
+
+/// A size like `512 B`, `3.4 MiB` or `1.2 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// A short duration like `45s`, `5m`, `2h13m` or `3d4h`.
+pub fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{minutes}m"),
+        3600..86400 => format!("{hours}h{minutes}m"),
+        _ => format!("{days}d{hours}h"),
+    }
+}
+
+/// The effective user of portwitch itself.
+pub fn current_uid() -> Option<u32> {
+    #[cfg(unix)]
+    return Some(nix::unistd::geteuid().as_raw());
+    #[cfg(windows)]
+    return None;
+}
+
+/// The ID of the user with this name. Only users in `/etc/passwd` are known,
+/// not the ones of a directory service.
+pub fn user_id(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    return std::fs::read_to_string("/etc/passwd")
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            // Lines look like `root:x:0:0:root:/root:/bin/sh`
+            let mut fields = line.split(':');
+            if fields.next()? != name {
+                return None;
+            }
+            fields.nth(1)?.parse().ok()
+        });
+    #[cfg(windows)]
+    return {
+        let _ = name;
+        None
+    };
+}
+
+/// The local time of day like `12:03:11`.
+pub fn format_clock(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let seconds = (seconds + utc_offset()).rem_euclid(86400);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// How long an offset is used before `date` is asked again, so a change to or from
+/// daylight saving time shows up without a restart.
+const UTC_OFFSET_AGE: Duration = Duration::from_secs(60);
+
+/// Seconds east of UTC. The standard library does not know the time zone,
+/// but `date` does. Falls back to UTC.
+fn utc_offset() -> i64 {
+    static OFFSET: Mutex<Option<(Instant, i64)>> = Mutex::new(None);
+    let mut cached = OFFSET.lock().unwrap();
+    if let Some((read, offset)) = *cached
+        && read.elapsed() < UTC_OFFSET_AGE
+    {
+        return offset;
+    }
+    let offset = Command::new("date")
+        .arg("+%z")
+        .output()
+        .ok()
+        .and_then(|output| parse_utc_offset(String::from_utf8_lossy(&output.stdout).trim()))
+        .unwrap_or(0);
+    *cached = Some((Instant::now(), offset));
+    offset
+}
+
+/// Parse offsets like `+0200` or `-0530`.
+fn parse_utc_offset(offset: &str) -> Option<i64> {
+    let (sign, digits) = match offset.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_offsets() {
+        assert_eq!(parse_utc_offset("+0000"), Some(0));
+        assert_eq!(parse_utc_offset("+0200"), Some(7200));
+        assert_eq!(parse_utc_offset("-0530"), Some(-19800));
+        assert_eq!(parse_utc_offset("0200"), None);
+        assert_eq!(parse_utc_offset(""), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn elapsed() {
+        let secs = |s| parse_elapsed(s).map(|d| d.as_secs());
+        assert_eq!(secs("00:05"), Some(5));
+        assert_eq!(secs("12:34"), Some(12 * 60 + 34));
+        assert_eq!(secs("01:02:03"), Some(3723));
+        assert_eq!(secs("3-01:02:03"), Some(3 * 86400 + 3723));
+        assert_eq!(parse_elapsed("0:01.25"), Some(Duration::from_millis(1250)));
+        assert_eq!(secs(""), None);
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 + 400 * 1024), "3.4 MiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn uptime() {
+        let format = |secs| format_uptime(Duration::from_secs(secs));
+        assert_eq!(format(45), "45s");
+        assert_eq!(format(5 * 60 + 3), "5m");
+        assert_eq!(format(2 * 3600 + 13 * 60), "2h13m");
+        assert_eq!(format(3 * 86400 + 4 * 3600), "3d4h");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn own_start_time() {
+        let started = start_time(std::process::id() as usize).unwrap();
+        assert!(started.elapsed().unwrap() < Duration::from_secs(600));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn own_arguments() {
+        let pid = std::process::id() as usize;
+        let args = arguments(pid).unwrap();
+        assert_eq!(args.first(), std::env::args_os().next().as_ref());
+        assert_eq!(working_dir(pid), std::env::current_dir().ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn own_sample() {
+        let sample = sample(std::process::id() as usize).unwrap();
+        assert!(sample.memory > 0);
+    }
+}
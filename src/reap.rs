@@ -0,0 +1,95 @@
+//! Kill whatever listens on a port, without the TUI.
+
+use portwitch::kill::{Signal, Target, kill};
+use portwitch::{PortSource, Process, ScanOptions, processes};
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// Kill the processes listening on the port, every interval until interrupted,
+/// or a single time with `once`.
+pub fn reap(
+    source: &dyn PortSource,
+    port: u16,
+    signal: Signal,
+    interval: Duration,
+    once: bool,
+) -> io::Result<()> {
+    loop {
+        match reap_once(source, port, signal) {
+            Ok(found) if once => {
+                if found == 0 {
+                    println!("Nothing listens on port {port}.");
+                }
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) if once => return Err(e),
+            // Keep reaping, the next scan may work again
+            Err(e) => eprintln!("portwitch: {e}"),
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Returns the number of processes that listen on the port, even if killing them failed.
+fn reap_once(source: &dyn PortSource, port: u16, signal: Signal) -> io::Result<usize> {
+    let scan = processes(source, ScanOptions::default())?;
+
+    let mut found = 0;
+    for process in listeners(&scan.processes, port) {
+        match kill(Target::Process(process.pid), signal) {
+            Ok(()) => println!(
+                "Sent SIG{signal} to {} ({}) on port {port}.",
+                process.command, process.pid
+            ),
+            Err(e) => eprintln!(
+                "portwitch: could not kill {} ({}): {e}",
+                process.command, process.pid
+            ),
+        }
+        found += 1;
+    }
+    Ok(found)
+}
+
+/// The processes listening on the port. A connection from or to the port is no reason to kill.
+fn listeners(processes: &[Process], port: u16) -> impl Iterator<Item = &Process> {
+    processes
+        .iter()
+        .filter(move |p| p.ports.iter().any(|s| s.port == port && s.remote.is_none()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use portwitch::test_support::process;
+
+    fn connected(pid: usize, port: u16, remote: &str) -> Process {
+        let mut process = process(pid, "node", port);
+        process.ports[0].remote = Some(remote.to_string());
+        process
+    }
+
+    #[test]
+    fn only_listeners_on_the_port() {
+        let processes = [
+            process(1, "node", 3000),
+            process(2, "node", 3001),
+            // A client of port 3000 uses another local port, but a server's
+            // accepted connection has the listening port as its local port
+            connected(3, 3000, "127.0.0.1:51234"),
+            connected(4, 51234, "127.0.0.1:3000"),
+            process(5, "node", 3000),
+        ];
+        let pids = |port| {
+            listeners(&processes, port)
+                .map(|p| p.pid)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pids(3000), [1, 5]);
+        assert_eq!(pids(3001), [2]);
+        assert_eq!(pids(51234), []);
+        assert_eq!(pids(8080), []);
+    }
+}
@@ -0,0 +1,120 @@
+//! Names of well-known ports, like `https` for 443, from `/etc/services`.
+
+use crate::source::Protocol;
+use std::fs;
+use std::sync::OnceLock;
+
+/// Used when there is no `/etc/services`, like on Windows.
+const FALLBACK: [(&str, u16); 20] = [
+    ("ftp", 21),
+    ("ssh", 22),
+    ("telnet", 23),
+    ("smtp", 25),
+    ("domain", 53),
+    ("http", 80),
+    ("pop3", 110),
+    ("imap", 143),
+    ("ldap", 389),
+    ("https", 443),
+    ("submission", 587),
+    ("imaps", 993),
+    ("pop3s", 995),
+    ("ms-sql-s", 1433),
+    ("mysql", 3306),
+    ("rdp", 3389),
+    ("postgresql", 5432),
+    ("redis", 6379),
+    ("http-alt", 8080),
+    ("mongodb", 27017),
+];
+
+#[derive(Debug, Eq, PartialEq)]
+struct Service {
+    name: String,
+    port: u16,
+    protocol: Protocol,
+}
+
+/// The services of the system, read once.
+fn services() -> &'static [Service] {
+    static SERVICES: OnceLock<Vec<Service>> = OnceLock::new();
+    SERVICES.get_or_init(|| match fs::read_to_string("/etc/services") {
+        Ok(text) => parse_services(&text),
+        Err(_) => FALLBACK
+            .iter()
+            .flat_map(|&(name, port)| {
+                [Protocol::Tcp, Protocol::Udp].map(|protocol| Service {
+                    name: name.to_string(),
+                    port,
+                    protocol,
+                })
+            })
+            .collect(),
+    })
+}
+
+/// The name of the service that usually runs on this port, like `postgresql` for 5432.
+pub fn name(protocol: Protocol, port: u16) -> Option<&'static str> {
+    services()
+        .iter()
+        .find(|s| s.protocol == protocol && s.port == port)
+        .map(|s| s.name.as_str())
+}
+
+/// The port of a service name, like 5432 for `postgresql`.
+pub fn port(protocol: Protocol, name: &str) -> Option<u16> {
+    services()
+        .iter()
+        .find(|s| s.protocol == protocol && s.name == name)
+        .map(|s| s.port)
+}
+
+/// Parse lines like `https  443/tcp  # comment`. Aliases after the port are skipped,
+/// and so are protocols other than TCP and UDP.
+fn parse_services(text: &str) -> Vec<Service> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            let mut words = line.split_whitespace();
+            let name = words.next()?;
+            let (port, protocol) = words.next()?.split_once('/')?;
+            let protocol = match protocol {
+                "tcp" => Protocol::Tcp,
+                "udp" => Protocol::Udp,
+                _ => return None,
+            };
+            Some(Service {
+                name: name.to_string(),
+                port: port.parse().ok()?,
+                protocol,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn etc_services() {
+        let text = "# Network services\n\
+                    \n\
+                    http\t\t80/tcp\t\twww\t# WorldWideWeb HTTP\n\
+                    https\t\t443/udp\n\
+                    sctp-thing\t9/sctp\n\
+                    broken\t\tnope/tcp\n";
+        let service = |name: &str, port, protocol| Service {
+            name: name.to_string(),
+            port,
+            protocol,
+        };
+        assert_eq!(
+            parse_services(text),
+            [
+                service("http", 80, Protocol::Tcp),
+                service("https", 443, Protocol::Udp),
+            ]
+        );
+    }
+}
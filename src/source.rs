@@ -0,0 +1,260 @@
+use crate::services;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::SystemTime;
+use std::{fmt, io};
+use strum::Display;
+
+/// A way of finding the processes that listen on ports.
+pub trait PortSource: Send + Sync + fmt::Debug {
+    fn scan(&self, options: ScanOptions) -> io::Result<Scan>;
+}
+
+/// The result of a successful scan.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub processes: Vec<Process>,
+    /// Problems that did not stop the scan, but may make it incomplete.
+    pub warnings: Vec<String>,
+    /// The established connections of each listening port, if the scan counted them.
+    pub connection_counts: HashMap<(Protocol, u16), usize>,
+}
+
+impl From<Vec<Process>> for Scan {
+    fn from(processes: Vec<Process>) -> Self {
+        Self {
+            processes,
+            warnings: Vec::new(),
+            connection_counts: HashMap::new(),
+        }
+    }
+}
+
+/// What a scan should look for.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Also keep established connections, not only listening sockets.
+    pub connections: bool,
+    /// Look up when each process was started. This costs extra work per process.
+    pub uptime: bool,
+    /// Only keep sockets of this protocol, or both if `None`.
+    pub protocol: Option<Protocol>,
+    /// Count the established connections of each listening port. The sources have to look
+    /// at every connection for this, even if they are not kept.
+    pub count_connections: bool,
+}
+
+impl ScanOptions {
+    /// Whether sockets of this protocol should be kept.
+    pub fn wants(&self, protocol: Protocol) -> bool {
+        self.protocol.is_none_or(|p| p == protocol)
+    }
+
+    /// Whether sources should report connected sockets, for showing or counting them.
+    pub fn reads_connections(&self) -> bool {
+        self.connections || self.count_connections
+    }
+}
+
+/// The best source available for this platform.
+pub fn default_source() -> Box<dyn PortSource> {
+    #[cfg(target_os = "linux")]
+    if crate::ss::Ss::available() {
+        return Box::new(crate::ss::Ss);
+    }
+    #[cfg(unix)]
+    return Box::new(crate::lsof::Lsof);
+    #[cfg(windows)]
+    return Box::new(crate::netstat::Netstat);
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Process {
+    pub pid: usize,
+    /// The process group, if the source knows it.
+    pub pgid: Option<usize>,
+    /// The parent process, if the source knows it.
+    pub ppid: Option<usize>,
+    /// The user that runs the process, if the source knows it.
+    pub uid: Option<u32>,
+    /// Only known if the scan asked for the uptime.
+    pub started: Option<SystemTime>,
+    pub command: String,
+    pub ports: Vec<Socket>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Socket {
+    pub protocol: Protocol,
+    pub family: Family,
+    /// The bind address without port, e.g. `127.0.0.1`, `*` or `::1`.
+    pub address: String,
+    pub port: u16,
+    /// The peer of a connected socket, e.g. `93.184.216.34:443`.
+    pub remote: Option<String>,
+    /// The file descriptor, for debugging. Only lsof reports it.
+    pub fd: Option<String>,
+    /// The inode of the socket, for debugging. Only lsof reports it, and not on every platform.
+    pub inode: Option<String>,
+}
+
+impl Socket {
+    /// Parse a local address like `127.0.0.1:8080`, `[::]:80` or `*:443`.
+    /// Wildcard binds of either family are stored as `*`, and interface
+    /// suffixes like `%lo` are dropped. `*` is assumed to be IPv4, sources
+    /// that know better should set the family themselves.
+    /// Service names like `*:http` are looked up, lsof prints them without `-P`.
+    pub fn parse_local(protocol: Protocol, local: &str) -> Option<Socket> {
+        let (host, port) = split_host(local)?;
+        let port = port
+            .parse()
+            .ok()
+            .or_else(|| services::port(protocol, port))?;
+        let host = host.split_once('%').map_or(host, |(host, _iface)| host);
+        let family = if host.contains(':') {
+            Family::V6
+        } else {
+            Family::V4
+        };
+        let address = match host {
+            "0.0.0.0" | "::" => "*",
+            host => host,
+        };
+        Some(Socket {
+            protocol,
+            family,
+            address: address.to_string(),
+            port,
+            remote: None,
+            fd: None,
+            inode: None,
+        })
+    }
+
+    /// Bound to `127.0.0.1`, `::1` or another loopback address, so it cannot be reached
+    /// from the network.
+    pub fn is_loopback(&self) -> bool {
+        match self.address.parse::<IpAddr>() {
+            Ok(ip) => ip.is_loopback(),
+            Err(_) => self.address == "localhost",
+        }
+    }
+
+    /// The protocol with a `6` for IPv6 sockets, e.g. `tcp` or `udp6`.
+    pub fn kind(&self) -> String {
+        match self.family {
+            Family::V4 => self.protocol.to_string(),
+            Family::V6 => format!("{}6", self.protocol),
+        }
+    }
+
+    /// The address and port as lsof prints them, e.g. `*:80` or `[::1]:8080`.
+    pub fn network(&self) -> String {
+        if self.address.contains(':') {
+            format!("[{}]:{}", self.address, self.port)
+        } else {
+            format!("{}:{}", self.address, self.port)
+        }
+    }
+
+    /// What makes two sockets the same. A process can have a socket open
+    /// on several file descriptors, which does not make it another socket.
+    pub(crate) fn key(&self) -> (Protocol, Family, &str, u16, Option<&str>) {
+        (
+            self.protocol,
+            self.family,
+            &self.address,
+            self.port,
+            self.remote.as_deref(),
+        )
+    }
+}
+
+impl PartialEq for Socket {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Socket {}
+
+impl std::hash::Hash for Socket {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl fmt::Display for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.port, self.kind())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Display)]
+pub enum Protocol {
+    #[strum(to_string = "tcp")]
+    Tcp,
+    #[strum(to_string = "udp")]
+    Udp,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Display)]
+pub enum Family {
+    #[strum(to_string = "ipv4")]
+    V4,
+    #[strum(to_string = "ipv6")]
+    V6,
+}
+
+/// Ports that more than one process listens on, e.g. during a restart race.
+pub fn shared_ports(processes: &[Process]) -> HashSet<(Protocol, u16)> {
+    let mut owners = HashMap::new();
+    let mut shared = HashSet::new();
+    for process in processes {
+        for socket in process.ports.iter().filter(|s| s.remote.is_none()) {
+            let key = (socket.protocol, socket.port);
+            match owners.get(&key) {
+                Some(&pid) if pid != process.pid => {
+                    shared.insert(key);
+                }
+                Some(_) => {}
+                None => {
+                    owners.insert(key, process.pid);
+                }
+            }
+        }
+    }
+    shared
+}
+
+/// How many established connections each listening port has, by protocol and port.
+/// A connection counts for a listener if its local port is the one the listener is on,
+/// which is the case for the connections it accepted.
+pub fn connection_counts(processes: &[Process]) -> HashMap<(Protocol, u16), usize> {
+    let sockets = || processes.iter().flat_map(|p| &p.ports);
+    let mut counts: HashMap<_, _> = sockets()
+        .filter(|s| s.remote.is_none())
+        .map(|s| ((s.protocol, s.port), 0))
+        .collect();
+    for socket in sockets().filter(|s| s.remote.is_some()) {
+        if let Some(count) = counts.get_mut(&(socket.protocol, socket.port)) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// Split a network address like `127.0.0.1:8080`, `*:443` or `[::1]:8080` into host and port.
+pub fn split_host_port(network: &str) -> Option<(&str, u16)> {
+    let (host, port) = split_host(network)?;
+    Some((host, port.parse().ok()?))
+}
+
+fn split_host(network: &str) -> Option<(&str, &str)> {
+    let (host, port) = network.rsplit_once(':')?;
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']')?,
+        None => host,
+    };
+    Some((host, port))
+}
@@ -0,0 +1,222 @@
+use crate::source::{PortSource, Process, Protocol, Scan, ScanOptions, Socket};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::{fs, io};
+
+/// Finds listening processes on Linux with `ss`, which is much faster than lsof.
+#[derive(Debug)]
+pub struct Ss;
+
+impl Ss {
+    /// Check if `ss` can be run on this machine.
+    pub fn available() -> bool {
+        Command::new("ss").arg("--version").output().is_ok()
+    }
+}
+
+impl PortSource for Ss {
+    fn scan(&self, options: ScanOptions) -> io::Result<Scan> {
+        let mut command = Command::new("ss");
+        // Always both protocols, ss leaves out the Netid column for only one of them.
+        // The options filter them while parsing.
+        command.args(["--numeric", "--processes", "--no-header", "--tcp", "--udp"]);
+        if options.reads_connections() {
+            command.arg("--all");
+        } else {
+            command.arg("--listening");
+        }
+        let output = command
+            .output()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to run ss: {e}")))?;
+        // Otherwise a failing ss would look like nothing is listening
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "ss failed with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+        let processes = parse_ss_output(&String::from_utf8_lossy(&output.stdout), options);
+        Ok(processes.into())
+    }
+}
+
+/// Parse lines like
+/// `tcp LISTEN 0 128 127.0.0.1:631 0.0.0.0:* users:(("cupsd",pid=1,fd=7))`.
+/// Sockets without a process (owned by other users) are skipped.
+/// Without the Netid column, a listening socket is TCP and an unconnected one UDP.
+fn parse_ss_output(out: &str, options: ScanOptions) -> Vec<Process> {
+    let mut processes: BTreeMap<usize, Process> = BTreeMap::new();
+
+    for line in out.lines() {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let (netid, columns) = match columns.as_slice() {
+            ["tcp", columns @ ..] => (Some(Protocol::Tcp), columns),
+            ["udp", columns @ ..] => (Some(Protocol::Udp), columns),
+            columns => (None, columns),
+        };
+        let [state, _recv, _send, local, peer, ..] = columns else {
+            continue;
+        };
+        let protocol = match (netid, *state) {
+            (Some(protocol), _) => protocol,
+            (None, "LISTEN") => Protocol::Tcp,
+            (None, "UNCONN") => Protocol::Udp,
+            _ => continue,
+        };
+        if !options.wants(protocol) {
+            continue;
+        }
+        let remote = match *state {
+            "LISTEN" | "UNCONN" => None,
+            "ESTAB" if options.reads_connections() => Some(peer.to_string()),
+            _ => continue,
+        };
+        let Some(mut socket) = Socket::parse_local(protocol, local) else {
+            continue;
+        };
+        socket.remote = remote;
+
+        // Taken from the line as it is, commands may contain spaces
+        let users = line.find("users:").map_or("", |start| &line[start..]);
+        for (command, pid) in parse_users(users) {
+            let process = processes.entry(pid).or_insert_with(|| Process {
+                pid,
+                pgid: read_pgid(pid),
+                ppid: crate::procinfo::parent_pid(pid),
+                uid: read_uid(pid),
+                started: None,
+                command: command.to_string(),
+                ports: Vec::new(),
+            });
+            if !process.ports.contains(&socket) {
+                process.ports.push(socket.clone());
+            }
+        }
+    }
+
+    processes.into_values().collect()
+}
+
+/// Read the process group from `/proc/<pid>/stat`.
+fn read_pgid(pid: usize) -> Option<usize> {
+    parse_pgid(&fs::read_to_string(format!("/proc/{pid}/stat")).ok()?)
+}
+
+fn parse_pgid(stat: &str) -> Option<usize> {
+    // The command in parentheses may contain spaces, the fields after it are
+    // state, ppid and pgrp.
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(2)?.parse().ok()
+}
+
+/// The owner of `/proc/<pid>` is the effective user of the process.
+fn read_uid(pid: usize) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(fs::metadata(format!("/proc/{pid}")).ok()?.uid())
+}
+
+/// Parse `users:(("name",pid=1,fd=3),("other",pid=2,fd=4))` into names and PIDs.
+fn parse_users(users: &str) -> Vec<(&str, usize)> {
+    users
+        .split("(\"")
+        .skip(1)
+        .filter_map(|user| {
+            let (name, rest) = user.split_once("\",pid=")?;
+            let pid = rest.split([',', ')']).next()?.parse().ok()?;
+            Some((name, pid))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX: &str = include_str!("../tests/fixtures/ss-linux.txt");
+    const TCP_ONLY: &str = include_str!("../tests/fixtures/ss-tcp-only.txt");
+
+    fn ports(processes: &[Process]) -> Vec<(usize, Protocol, u16)> {
+        processes
+            .iter()
+            .flat_map(|p| p.ports.iter().map(|s| (p.pid, s.protocol, s.port)))
+            .collect()
+    }
+
+    #[test]
+    fn without_netid_column() {
+        let processes = parse_ss_output(TCP_ONLY, ScanOptions::default());
+        assert_eq!(
+            ports(&processes),
+            [
+                (121, Protocol::Tcp, 48271),
+                (801, Protocol::Tcp, 80),
+                (802, Protocol::Tcp, 80),
+            ]
+        );
+        let udp = ScanOptions {
+            protocol: Some(Protocol::Udp),
+            ..ScanOptions::default()
+        };
+        assert!(parse_ss_output(TCP_ONLY, udp).is_empty());
+    }
+
+    #[test]
+    fn listeners() {
+        let processes = parse_ss_output(LINUX, ScanOptions::default());
+        assert_eq!(
+            ports(&processes),
+            [
+                (610, Protocol::Udp, 5353),
+                // Both nginx workers have the IPv4 and the IPv6 socket
+                (801, Protocol::Tcp, 80),
+                (801, Protocol::Tcp, 80),
+                (802, Protocol::Tcp, 80),
+                (802, Protocol::Tcp, 80),
+                (933, Protocol::Tcp, 6010),
+            ]
+        );
+        assert_eq!(processes[3].command, "tmux: server");
+    }
+
+    #[test]
+    fn connections() {
+        let options = ScanOptions {
+            connections: true,
+            ..ScanOptions::default()
+        };
+        let processes = parse_ss_output(LINUX, options);
+        let resolver = processes.iter().find(|p| p.pid == 700).unwrap();
+        assert_eq!(resolver.ports[0].protocol, Protocol::Udp);
+        assert_eq!(resolver.ports[0].remote.as_deref(), Some("1.1.1.1:53"));
+        let nginx = processes.iter().find(|p| p.pid == 802).unwrap();
+        let remotes = nginx.ports.iter().filter_map(|s| s.remote.as_deref());
+        // TIME-WAIT has no process and is not a connection anymore
+        assert_eq!(remotes.collect::<Vec<_>>(), ["10.0.0.2:50312"]);
+    }
+
+    #[test]
+    fn users() {
+        assert_eq!(
+            parse_users(r#"users:(("nginx",pid=802,fd=6),("nginx",pid=801,fd=6))"#),
+            [("nginx", 802), ("nginx", 801)]
+        );
+        assert_eq!(
+            parse_users(r#"users:(("tmux: server",pid=933,fd=4))"#),
+            [("tmux: server", 933)]
+        );
+        assert_eq!(parse_users(""), []);
+        assert_eq!(parse_users(r#"users:(("broken",pid=x,fd=1))"#), []);
+    }
+
+    #[test]
+    fn process_group() {
+        let stat = "933 (tmux: server) S 1 933 933 0 -1 4194624";
+        assert_eq!(parse_pgid(stat), Some(933));
+        // A command with a parenthesis of its own
+        let stat = "42 (evil) S 7) S 1 40 40 0";
+        assert_eq!(parse_pgid(stat), Some(40));
+        assert_eq!(parse_pgid("42 (truncated"), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Settings changed in the UI that are kept across restarts,
+//! in `~/.config/portwitch/state.toml`.
+
+use crate::SortKey;
+use crate::config::{config_dir, parse_toml};
+use crate::filter::FilterMode;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct State {
+    pub sort: SortKey,
+    pub sort_reversed: bool,
+    pub filter_mode: FilterMode,
+    /// Only set if it was changed in the UI, and only used if the config has none.
+    pub update_interval: Option<Duration>,
+}
+
+impl State {
+    /// Load the state file. Anything that cannot be read keeps its default.
+    pub fn load() -> Self {
+        let Some(text) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return Self::default();
+        };
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut state = Self::default();
+        for (key, value) in parse_toml(text).unwrap_or_default() {
+            match key.as_str() {
+                "sort" => {
+                    if let Some(sort) = value.into_string().ok().and_then(|s| s.parse().ok()) {
+                        state.sort = sort;
+                    }
+                }
+                "sort_reversed" => {
+                    if let Ok(reversed) = value.into_bool() {
+                        state.sort_reversed = reversed;
+                    }
+                }
+                "filter_mode" => {
+                    if let Some(mode) = value.into_string().ok().and_then(|s| s.parse().ok()) {
+                        state.filter_mode = mode;
+                    }
+                }
+                "update_interval_ms" => {
+                    if let Some(ms) = value.into_integer().ok().and_then(|ms| ms.try_into().ok()) {
+                        state.update_interval = Some(Duration::from_millis(ms));
+                    }
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    fn to_toml(self) -> String {
+        let mut toml = format!(
+            "sort = \"{}\"\nsort_reversed = {}\nfilter_mode = \"{}\"\n",
+            self.sort, self.sort_reversed, self.filter_mode
+        );
+        if let Some(interval) = self.update_interval {
+            toml += &format!("update_interval_ms = {}\n", interval.as_millis());
+        }
+        toml
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("state.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let state = State {
+            sort: SortKey::Port,
+            sort_reversed: true,
+            filter_mode: FilterMode::Regex,
+            update_interval: Some(Duration::from_secs(2)),
+        };
+        assert_eq!(State::parse(&state.to_toml()), state);
+    }
+
+    #[test]
+    fn broken_values_are_ignored() {
+        let state = State::parse("sort = \"color\"\nsort_reversed = true\nfilter_mode = 3\n");
+        assert_eq!(
+            state,
+            State {
+                sort_reversed: true,
+                ..State::default()
+            }
+        );
+    }
+}
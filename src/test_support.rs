@@ -0,0 +1,41 @@
+//! Processes and a source for the tests of the library and of the binary.
+//! Not part of the API.
+
+use crate::source::{Family, PortSource, Process, Protocol, Scan, ScanOptions, Socket};
+use std::io;
+
+/// A source that finds the processes made by the function.
+#[derive(Debug)]
+pub struct FixedSource(pub fn() -> Vec<Process>);
+
+impl PortSource for FixedSource {
+    fn scan(&self, _options: ScanOptions) -> io::Result<Scan> {
+        Ok((self.0)().into())
+    }
+}
+
+/// A process that listens on `*:<port>` over TCP.
+pub fn process(pid: usize, command: &str, port: u16) -> Process {
+    Process {
+        pid,
+        pgid: None,
+        ppid: None,
+        uid: None,
+        started: None,
+        command: command.to_string(),
+        ports: vec![socket(port)],
+    }
+}
+
+/// A TCP socket listening on `*:<port>`.
+pub fn socket(port: u16) -> Socket {
+    Socket {
+        protocol: Protocol::Tcp,
+        family: Family::V4,
+        address: "*".to_string(),
+        port,
+        remote: None,
+        fd: None,
+        inode: None,
+    }
+}
@@ -0,0 +1,161 @@
+//! Colors of the UI.
+
+use ratatui::style::{Color, Style};
+use std::str::FromStr;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// The styles used by the UI, by what they are used for.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Theme {
+    /// The process list.
+    pub text: Style,
+    /// The selected row.
+    pub highlight: Style,
+    /// Drawn in front of the selected row.
+    pub highlight_symbol: String,
+    /// Characters of the command matched by a fuzzy filter.
+    pub matched: Style,
+    /// Rows of processes on watched ports.
+    pub watched: Style,
+    /// Rows of processes that just started listening.
+    pub appeared: Style,
+    /// Rows of processes that just exited.
+    pub gone: Style,
+    /// The filter in the title. It is reversed while it is edited.
+    pub filter: Style,
+    /// A regex filter that does not compile.
+    pub invalid_filter: Style,
+    /// Markers in the title like ` paused `.
+    pub badge: Style,
+    pub warning: Style,
+    /// Status messages and tips.
+    pub accent: Style,
+    /// The error popup.
+    pub error: Style,
+}
+
+const DEFAULT_SYMBOL: &str = ">";
+
+/// The built-in themes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, EnumIter, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ThemeName {
+    /// Bright colors for terminals with a dark background.
+    #[default]
+    Dark,
+    /// Darker colors for terminals with a light background.
+    Light,
+    /// No colors at all, only bold, underlined and reversed text. Used for `NO_COLOR`.
+    Plain,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Dark => Theme {
+                text: Style::new().white(),
+                highlight: Style::new().light_red().bold(),
+                highlight_symbol: DEFAULT_SYMBOL.to_string(),
+                matched: Style::new().light_yellow().bold(),
+                watched: Style::new().light_green(),
+                appeared: Style::new().black().on_light_green(),
+                gone: Style::new().black().on_light_red(),
+                filter: Style::new().light_blue(),
+                invalid_filter: Style::new().yellow(),
+                badge: Style::new().black().on_yellow(),
+                warning: Style::new().yellow(),
+                accent: Style::new().yellow(),
+                error: Style::new().light_red(),
+            },
+            ThemeName::Light => Theme {
+                text: Style::new().black(),
+                highlight: Style::new().red().bold(),
+                highlight_symbol: DEFAULT_SYMBOL.to_string(),
+                matched: Style::new().blue().bold(),
+                watched: Style::new().green(),
+                appeared: Style::new().white().on_green(),
+                gone: Style::new().white().on_red(),
+                filter: Style::new().blue(),
+                invalid_filter: Style::new().magenta(),
+                badge: Style::new().white().on_blue(),
+                warning: Style::new().fg(Color::Rgb(176, 96, 0)),
+                accent: Style::new().magenta(),
+                error: Style::new().red(),
+            },
+            ThemeName::Plain => Theme {
+                text: Style::new(),
+                highlight: Style::new().reversed().bold(),
+                highlight_symbol: DEFAULT_SYMBOL.to_string(),
+                matched: Style::new().underlined(),
+                watched: Style::new().underlined(),
+                appeared: Style::new().bold(),
+                gone: Style::new().crossed_out(),
+                filter: Style::new(),
+                invalid_filter: Style::new().underlined(),
+                badge: Style::new().reversed(),
+                warning: Style::new().bold(),
+                accent: Style::new().bold(),
+                error: Style::new(),
+            },
+        }
+    }
+}
+
+/// Parse a style like `bold`, `light_red bold` or `black on_yellow`.
+/// Colors are the ones ratatui knows, like `red`, `#ff8000` or `208`.
+pub fn parse_style(text: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    for word in text.split_whitespace() {
+        style = match word {
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "italic" => style.italic(),
+            "underlined" => style.underlined(),
+            "reversed" => style.reversed(),
+            "crossed_out" => style.crossed_out(),
+            _ => match word.strip_prefix("on_") {
+                Some(color) => style.bg(parse_color(color)?),
+                None => style.fg(parse_color(word)?),
+            },
+        };
+    }
+    Ok(style)
+}
+
+fn parse_color(name: &str) -> Result<Color, String> {
+    name.parse()
+        .map_err(|_| format!("unknown color or modifier `{name}`"))
+}
+
+impl FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        ThemeName::iter()
+            .find(|theme| theme.to_string() == name)
+            .ok_or_else(|| format!("unknown theme `{s}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles() {
+        assert_eq!(parse_style("reversed"), Ok(Style::new().reversed()));
+        assert_eq!(
+            parse_style(" light_red  bold "),
+            Ok(Style::new().light_red().bold())
+        );
+        assert_eq!(
+            parse_style("black on_#ffcc00"),
+            Ok(Style::new().black().bg(Color::Rgb(255, 204, 0)))
+        );
+        assert_eq!(
+            parse_style("blinking"),
+            Err("unknown color or modifier `blinking`".to_string())
+        );
+    }
+}